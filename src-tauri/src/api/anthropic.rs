@@ -154,7 +154,11 @@ impl AnthropicClient {
     /// let client = AnthropicClient::new("sk-ant-...".to_string());
     /// ```
     pub fn new(api_key: String) -> Self {
-        let client = reqwest::Client::new();
+        Self::with_client(api_key, crate::api::http::default_client())
+    }
+
+    /// Create a client that reuses an existing `reqwest::Client`
+    pub fn with_client(api_key: String, client: reqwest::Client) -> Self {
         Self { api_key, client }
     }
 
@@ -1,10 +1,15 @@
 use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use crate::storage::credentials_file;
+use crate::storage::keychain::KeychainManager;
 
 const OAUTH_API_BASE: &str = "https://api.anthropic.com/api/oauth";
 const OAUTH_TOKEN_URL: &str = "https://console.anthropic.com/v1/oauth/token";
 const CLAUDE_CLIENT_ID: &str = "9d1c250a-e61b-44d9-88ed-5944d1962f5e";
+const KEYCHAIN_PROVIDER: &str = "anthropic";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ClaudeCredentials {
@@ -29,6 +34,67 @@ pub struct OAuthTokens {
     pub rate_limit_tier: Option<String>,
 }
 
+/// Where tokens from the app's own OAuth login are kept.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenStorage {
+    /// `~/.claude/.credentials.json`, shared with the Claude CLI.
+    #[default]
+    CredentialsFile,
+    /// The OS keychain, so no refresh token is written to disk.
+    Keychain,
+}
+
+enum TokenSource {
+    Keychain,
+    File(PathBuf),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RefreshPolicy {
+    /// Refresh this many seconds before the access token expires.
+    pub margin_secs: u64,
+    pub max_attempts: u32,
+    pub initial_backoff_ms: u64,
+}
+
+impl RefreshPolicy {
+    const DEFAULT: Self = Self {
+        margin_secs: 300,
+        max_attempts: 3,
+        initial_backoff_ms: 1000,
+    };
+
+    /// Access tokens live for hours; a margin near that would rotate the
+    /// single-use refresh token on every poll.
+    const MAX_MARGIN_SECS: u64 = 60 * 60;
+
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        if self.max_attempts == 0 {
+            return Err("max_attempts must be at least 1".to_string());
+        }
+        if self.margin_secs > Self::MAX_MARGIN_SECS {
+            return Err(format!("margin_secs must be at most {}", Self::MAX_MARGIN_SECS));
+        }
+        Ok(())
+    }
+
+    fn needs_refresh(&self, expires_at_ms: u64) -> bool {
+        expires_at_ms < ClaudeOAuthClient::now_ms() + self.margin_secs * 1000
+    }
+}
+
+impl Default for RefreshPolicy {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+static REFRESH_POLICY: RwLock<RefreshPolicy> = RwLock::new(RefreshPolicy::DEFAULT);
+static TOKEN_STORAGE: RwLock<TokenStorage> = RwLock::new(TokenStorage::CredentialsFile);
+static REFRESH_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
 #[derive(Debug, Deserialize)]
 struct TokenRefreshResponse {
     access_token: String,
@@ -95,9 +161,14 @@ pub enum ClaudeOAuthError {
     RefreshFailed(String),
     TokenExpiredNeedsReauth,
     InvalidCredentials(String),
+    Keychain(String),
     RequestError(reqwest::Error),
     ParseError(String),
     ApiError { status: u16, message: String },
+    /// HTTP 429; `retry_after` comes from the `Retry-After` header.
+    RateLimited { retry_after: Option<std::time::Duration> },
+    /// HTTP 503.
+    ServiceUnavailable { retry_after: Option<std::time::Duration> },
 }
 
 impl std::fmt::Display for ClaudeOAuthError {
@@ -108,15 +179,53 @@ impl std::fmt::Display for ClaudeOAuthError {
             Self::RefreshFailed(msg) => write!(f, "Token refresh failed: {}", msg),
             Self::TokenExpiredNeedsReauth => write!(f, "TOKEN_EXPIRED_NEEDS_REAUTH"),
             Self::InvalidCredentials(msg) => write!(f, "Invalid credentials: {}", msg),
+            Self::Keychain(msg) => write!(f, "Keychain error: {}", msg),
             Self::RequestError(e) => write!(f, "Request error: {}", e),
             Self::ParseError(msg) => write!(f, "Parse error: {}", msg),
             Self::ApiError { status, message } => write!(f, "API error ({}): {}", status, message),
+            Self::RateLimited { .. } => write!(f, "Rate limited by the API"),
+            Self::ServiceUnavailable { .. } => write!(f, "API temporarily unavailable"),
         }
     }
 }
 
 impl std::error::Error for ClaudeOAuthError {}
 
+impl ClaudeOAuthError {
+    /// Whether retrying the same request later could succeed.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::RefreshFailed(_)
+            | Self::RequestError(_)
+            | Self::RateLimited { .. }
+            | Self::ServiceUnavailable { .. } => true,
+            Self::ApiError { status, .. } => *status == 429 || *status >= 500,
+            _ => false,
+        }
+    }
+
+    /// How long the server asked us to wait before trying again, if it said.
+    pub fn retry_after(&self) -> Option<std::time::Duration> {
+        match self {
+            Self::RateLimited { retry_after } | Self::ServiceUnavailable { retry_after } => *retry_after,
+            _ => None,
+        }
+    }
+
+    async fn from_response(response: reqwest::Response) -> Self {
+        let status = response.status().as_u16();
+        let retry_after = crate::api::http::retry_after(response.headers());
+        match status {
+            429 => Self::RateLimited { retry_after },
+            503 => Self::ServiceUnavailable { retry_after },
+            _ => Self::ApiError {
+                status,
+                message: response.text().await.unwrap_or_default(),
+            },
+        }
+    }
+}
+
 impl From<reqwest::Error> for ClaudeOAuthError {
     fn from(err: reqwest::Error) -> Self {
         Self::RequestError(err)
@@ -131,75 +240,155 @@ pub struct ClaudeOAuthClient {
 }
 
 impl ClaudeOAuthClient {
-    pub fn from_credentials_file() -> Result<Self> {
-        let creds_path = Self::credentials_path()?;
-        let contents = std::fs::read_to_string(&creds_path)
-            .map_err(|_| ClaudeOAuthError::CredentialsNotFound)?;
+    pub async fn from_credentials_file() -> Result<Self> {
+        let (oauth, _) = Self::load_tokens().await?;
         
-        let creds: ClaudeCredentials = serde_json::from_str(&contents)
-            .map_err(|e| ClaudeOAuthError::ParseError(e.to_string()))?;
-        
-        let oauth = creds.claude_ai_oauth
-            .ok_or(ClaudeOAuthError::CredentialsNotFound)?;
-        
-        let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_millis() as u64;
-        
-        if oauth.expires_at < now {
+        if oauth.expires_at < Self::now_ms() {
             return Err(ClaudeOAuthError::CredentialsExpired);
         }
         
-        Ok(Self {
-            access_token: oauth.access_token,
-            client: reqwest::Client::new(),
-        })
+        Ok(Self::new(oauth.access_token))
+    }
+
+    /// Loads tokens from the keychain or the credentials file, refreshing them
+    /// first if they expire within the configured margin.
+    pub async fn from_credentials_file_with_refresh(client: &reqwest::Client) -> Result<Self> {
+        let policy = Self::refresh_policy();
+
+        let (oauth, _) = Self::load_tokens().await?;
+        if !policy.needs_refresh(oauth.expires_at) {
+            return Ok(Self::with_client(oauth.access_token, client.clone()));
+        }
+
+        // Only one refresh may be in flight: the refresh token is single-use, so a
+        // second concurrent exchange would invalidate the first caller's result.
+        let _guard = REFRESH_LOCK.lock().await;
+
+        // Another caller may have refreshed while we were waiting for the lock.
+        let (oauth, source) = Self::load_tokens().await?;
+        if !policy.needs_refresh(oauth.expires_at) {
+            return Ok(Self::with_client(oauth.access_token, client.clone()));
+        }
+
+        eprintln!("Claude OAuth token expires soon, attempting refresh...");
+        match Self::refresh_with_retry(client, &oauth.refresh_token, &policy).await {
+            Ok(new_tokens) => {
+                let access_token = new_tokens.access_token.clone();
+                tokio::task::spawn_blocking(move || Self::save_refreshed_tokens(&new_tokens, &oauth, &source))
+                    .await
+                    .map_err(|e| ClaudeOAuthError::ParseError(format!("Failed to save refreshed tokens: {}", e)))??;
+                eprintln!("Claude OAuth token refreshed successfully");
+                Ok(Self::with_client(access_token, client.clone()))
+            }
+            Err(e) if oauth.expires_at > Self::now_ms() => {
+                eprintln!("Claude OAuth token refresh failed, using current token until expiry: {}", e);
+                Ok(Self::with_client(oauth.access_token, client.clone()))
+            }
+            Err(e) => {
+                eprintln!("Claude OAuth token refresh failed: {}", e);
+                Err(ClaudeOAuthError::TokenExpiredNeedsReauth)
+            }
+        }
+    }
+
+    pub fn refresh_policy() -> RefreshPolicy {
+        REFRESH_POLICY.read().map(|p| p.clone()).unwrap_or_default()
+    }
+
+    pub fn set_refresh_policy(policy: RefreshPolicy) {
+        if let Ok(mut current) = REFRESH_POLICY.write() {
+            *current = policy;
+        }
+    }
+
+    pub fn token_storage() -> TokenStorage {
+        TOKEN_STORAGE.read().map(|s| *s).unwrap_or_default()
     }
 
-    pub async fn from_credentials_file_with_refresh() -> Result<Self> {
+    pub fn set_token_storage(storage: TokenStorage) {
+        if let Ok(mut current) = TOKEN_STORAGE.write() {
+            *current = storage;
+        }
+    }
+
+    /// With keychain storage selected, app-owned tokens in the keychain take
+    /// precedence over the shared credentials file. Otherwise the keychain isn't
+    /// consulted, so a leftover entry can't shadow fresh CLI credentials. A keychain
+    /// that can't be read (locked, no backend, blocked in a container) is skipped so
+    /// the file still works.
+    async fn load_tokens() -> Result<(OAuthTokens, TokenSource)> {
+        if Self::token_storage() == TokenStorage::Keychain {
+            let stored = tokio::task::spawn_blocking(Self::read_keychain_tokens)
+                .await
+                .map_err(|e| ClaudeOAuthError::Keychain(e.to_string()))
+                .and_then(|r| r);
+            match stored {
+                Ok(Some(tokens)) => return Ok((tokens, TokenSource::Keychain)),
+                Ok(None) => {}
+                Err(e) => eprintln!("Skipping keychain for Claude tokens: {}", e),
+            }
+        }
+
         let creds_path = Self::credentials_path()?;
-        let contents = std::fs::read_to_string(&creds_path)
+        let tokens = Self::read_oauth_tokens(&creds_path)?;
+        Ok((tokens, TokenSource::File(creds_path)))
+    }
+
+    fn read_keychain_tokens() -> Result<Option<OAuthTokens>> {
+        let stored = KeychainManager::new()
+            .get_oauth_tokens(KEYCHAIN_PROVIDER)
+            .map_err(|e| ClaudeOAuthError::Keychain(e.to_string()))?;
+
+        stored
+            .map(|json| serde_json::from_str(&json)
+                .map_err(|e| ClaudeOAuthError::ParseError(e.to_string())))
+            .transpose()
+    }
+
+    fn read_oauth_tokens(creds_path: &Path) -> Result<OAuthTokens> {
+        let contents = std::fs::read_to_string(creds_path)
             .map_err(|_| ClaudeOAuthError::CredentialsNotFound)?;
-        
+
         let creds: ClaudeCredentials = serde_json::from_str(&contents)
             .map_err(|e| ClaudeOAuthError::ParseError(e.to_string()))?;
-        
-        let oauth = creds.claude_ai_oauth
-            .ok_or(ClaudeOAuthError::CredentialsNotFound)?;
-        
-        let now = std::time::SystemTime::now()
+
+        creds.claude_ai_oauth
+            .ok_or(ClaudeOAuthError::CredentialsNotFound)
+    }
+
+    fn now_ms() -> u64 {
+        std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap()
-            .as_millis() as u64;
-        
-        if oauth.expires_at < now {
-            eprintln!("Claude OAuth token expired, attempting refresh...");
-            match Self::refresh_tokens(&oauth.refresh_token).await {
-                Ok(new_tokens) => {
-                    Self::save_refreshed_tokens(&new_tokens, &creds_path)?;
-                    eprintln!("Claude OAuth token refreshed successfully");
-                    Ok(Self {
-                        access_token: new_tokens.access_token,
-                        client: reqwest::Client::new(),
-                    })
-                }
-                Err(e) => {
-                    eprintln!("Claude OAuth token refresh failed: {}", e);
-                    Err(ClaudeOAuthError::TokenExpiredNeedsReauth)
+            .as_millis() as u64
+    }
+
+    async fn refresh_with_retry(
+        client: &reqwest::Client,
+        refresh_token: &str,
+        policy: &RefreshPolicy,
+    ) -> Result<TokenRefreshResponse> {
+        let mut delay = std::time::Duration::from_millis(policy.initial_backoff_ms);
+        let mut attempt = 1;
+
+        loop {
+            match Self::refresh_tokens(client, refresh_token).await {
+                Ok(tokens) => return Ok(tokens),
+                Err(e) if attempt < policy.max_attempts && e.is_transient() => {
+                    eprintln!(
+                        "Claude OAuth token refresh attempt {} failed, retrying in {:?}: {}",
+                        attempt, delay, e
+                    );
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                    attempt += 1;
                 }
+                Err(e) => return Err(e),
             }
-        } else {
-            Ok(Self {
-                access_token: oauth.access_token,
-                client: reqwest::Client::new(),
-            })
         }
     }
 
-    async fn refresh_tokens(refresh_token: &str) -> Result<TokenRefreshResponse> {
-        let client = reqwest::Client::new();
-        
+    async fn refresh_tokens(client: &reqwest::Client, refresh_token: &str) -> Result<TokenRefreshResponse> {
         let body = serde_json::json!({
             "grant_type": "refresh_token",
             "client_id": CLAUDE_CLIENT_ID,
@@ -215,40 +404,115 @@ impl ClaudeOAuthClient {
             .map_err(|e| ClaudeOAuthError::RefreshFailed(e.to_string()))?;
 
         if !response.status().is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(ClaudeOAuthError::RefreshFailed(error_text));
+            return Err(ClaudeOAuthError::from_response(response).await);
         }
 
+        // The old refresh token is spent once the server answers 200, so an
+        // unreadable body must not be retried.
         response.json().await
-            .map_err(|e| ClaudeOAuthError::RefreshFailed(e.to_string()))
+            .map_err(|e| ClaudeOAuthError::ParseError(e.to_string()))
     }
 
-    fn save_refreshed_tokens(tokens: &TokenRefreshResponse, creds_path: &PathBuf) -> Result<()> {
-        let now_ms = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_millis() as u64;
-        
-        let creds = serde_json::json!({
-            "claudeAiOauth": {
-                "accessToken": tokens.access_token,
-                "refreshToken": tokens.refresh_token,
-                "expiresAt": now_ms + (tokens.expires_in * 1000),
-                "scopes": ["user:inference", "user:profile"],
+    fn save_refreshed_tokens(
+        tokens: &TokenRefreshResponse,
+        previous: &OAuthTokens,
+        source: &TokenSource,
+    ) -> Result<()> {
+        match source {
+            TokenSource::Keychain => {
+                let updated = OAuthTokens {
+                    access_token: tokens.access_token.clone(),
+                    refresh_token: tokens.refresh_token.clone(),
+                    expires_at: Self::now_ms() + tokens.expires_in * 1000,
+                    ..previous.clone()
+                };
+                Self::write_keychain_tokens(&updated)
             }
-        });
-        
-        std::fs::write(creds_path, serde_json::to_string_pretty(&creds).unwrap())
-            .map_err(|e| ClaudeOAuthError::ParseError(format!("Failed to save refreshed tokens: {}", e)))?;
-        
-        Ok(())
+            TokenSource::File(creds_path) => Self::write_tokens(
+                creds_path,
+                &tokens.access_token,
+                &tokens.refresh_token,
+                tokens.expires_in,
+                None,
+            ),
+        }
     }
 
-    pub fn new(access_token: String) -> Self {
-        Self {
-            access_token,
-            client: reqwest::Client::new(),
+    /// Stores tokens from a fresh login in the chosen location.
+    ///
+    /// File storage keeps any other fields the Claude CLI wrote. Keychain storage
+    /// leaves the credentials file untouched, since it may belong to the CLI.
+    pub fn store_tokens(
+        access_token: &str,
+        refresh_token: &str,
+        expires_in: u64,
+        scopes: Option<Vec<String>>,
+        storage: TokenStorage,
+    ) -> Result<()> {
+        match storage {
+            TokenStorage::Keychain => Self::write_keychain_tokens(&OAuthTokens {
+                access_token: access_token.to_string(),
+                refresh_token: refresh_token.to_string(),
+                expires_at: Self::now_ms() + expires_in * 1000,
+                scopes: scopes.unwrap_or_else(|| vec!["user:inference".into(), "user:profile".into()]),
+                subscription_type: None,
+                rate_limit_tier: None,
+            }),
+            TokenStorage::CredentialsFile => {
+                let creds_path = Self::credentials_path()?;
+                Self::write_tokens(&creds_path, access_token, refresh_token, expires_in, scopes)?;
+                // Otherwise stale keychain tokens would keep shadowing the file.
+                KeychainManager::new()
+                    .delete_oauth_tokens(KEYCHAIN_PROVIDER)
+                    .map_err(|e| ClaudeOAuthError::Keychain(e.to_string()))
+            }
+        }
+    }
+
+    fn write_keychain_tokens(tokens: &OAuthTokens) -> Result<()> {
+        let json = serde_json::to_string(tokens)
+            .map_err(|e| ClaudeOAuthError::ParseError(e.to_string()))?;
+        KeychainManager::new()
+            .store_oauth_tokens(KEYCHAIN_PROVIDER, &json)
+            .map_err(|e| ClaudeOAuthError::Keychain(e.to_string()))
+    }
+
+    fn write_tokens(
+        creds_path: &Path,
+        access_token: &str,
+        refresh_token: &str,
+        expires_in: u64,
+        scopes: Option<Vec<String>>,
+    ) -> Result<()> {
+        let now_ms = Self::now_ms();
+
+        let mut fields = serde_json::Map::new();
+        fields.insert("accessToken".into(), access_token.into());
+        fields.insert("refreshToken".into(), refresh_token.into());
+        fields.insert("expiresAt".into(), (now_ms + expires_in * 1000).into());
+        if let Some(scopes) = scopes {
+            fields.insert("scopes".into(), scopes.into());
         }
+
+        credentials_file::update_json(creds_path, |root| {
+            let has_scopes = root
+                .get("claudeAiOauth")
+                .and_then(|o| o.get("scopes"))
+                .is_some();
+            if !has_scopes && !fields.contains_key("scopes") {
+                fields.insert("scopes".into(), vec!["user:inference", "user:profile"].into());
+            }
+            credentials_file::merge_oauth_fields(root, fields);
+        })
+        .map_err(|e| ClaudeOAuthError::ParseError(format!("Failed to save refreshed tokens: {}", e)))
+    }
+
+    pub fn new(access_token: String) -> Self {
+        Self::with_client(access_token, crate::api::http::default_client())
+    }
+
+    pub fn with_client(access_token: String, client: reqwest::Client) -> Self {
+        Self { access_token, client }
     }
 
     fn credentials_path() -> Result<PathBuf> {
@@ -257,8 +521,18 @@ impl ClaudeOAuthClient {
         Ok(home.join(".claude").join(".credentials.json"))
     }
 
-    pub fn has_credentials() -> bool {
-        Self::credentials_path()
+    pub async fn has_credentials() -> bool {
+        let in_keychain = Self::token_storage() == TokenStorage::Keychain
+            && tokio::task::spawn_blocking(|| {
+                KeychainManager::new()
+                    .get_oauth_tokens(KEYCHAIN_PROVIDER)
+                    .map(|t| t.is_some())
+                    .unwrap_or(false)
+            })
+            .await
+            .unwrap_or(false);
+
+        in_keychain || Self::credentials_path()
             .map(|p| p.exists())
             .unwrap_or(false)
     }
@@ -290,13 +564,8 @@ impl ClaudeOAuthClient {
             .send()
             .await?;
         
-        let status = response.status();
-        if !status.is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(ClaudeOAuthError::ApiError {
-                status: status.as_u16(),
-                message: error_text,
-            });
+        if !response.status().is_success() {
+            return Err(ClaudeOAuthError::from_response(response).await);
         }
         
         let response_text = response.text().await.map_err(|e| {
@@ -319,13 +588,8 @@ impl ClaudeOAuthClient {
             .send()
             .await?;
         
-        let status = response.status();
-        if !status.is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(ClaudeOAuthError::ApiError {
-                status: status.as_u16(),
-                message: error_text,
-            });
+        if !response.status().is_success() {
+            return Err(ClaudeOAuthError::from_response(response).await);
         }
         
         response.json().await.map_err(|e| {
@@ -72,7 +72,10 @@ pub struct GeminiClient {
 
 impl GeminiClient {
     pub fn new(api_key: String) -> Self {
-        let client = reqwest::Client::new();
+        Self::with_client(api_key, crate::api::http::default_client())
+    }
+
+    pub fn with_client(api_key: String, client: reqwest::Client) -> Self {
         Self { api_key, client }
     }
 
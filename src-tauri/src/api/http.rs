@@ -0,0 +1,136 @@
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::Duration;
+
+static DEFAULT_CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HttpSettings {
+    pub connect_timeout_secs: u64,
+    /// Maximum time between reads from the server.
+    pub read_timeout_secs: u64,
+    /// Upper bound for a whole request, including the body.
+    pub request_timeout_secs: u64,
+    /// Proxy for all requests, e.g. `http://proxy.corp:3128`. When unset the
+    /// `HTTP_PROXY`, `HTTPS_PROXY` and `NO_PROXY` environment variables apply.
+    pub proxy_url: Option<String>,
+    /// Comma-separated hosts that bypass `proxy_url`.
+    pub no_proxy: Option<String>,
+    /// PEM files with extra root certificates, e.g. for TLS-inspecting proxies.
+    pub ca_cert_paths: Vec<String>,
+}
+
+impl Default for HttpSettings {
+    fn default() -> Self {
+        Self {
+            connect_timeout_secs: 10,
+            read_timeout_secs: 30,
+            request_timeout_secs: 60,
+            proxy_url: None,
+            no_proxy: None,
+            ca_cert_paths: Vec::new(),
+        }
+    }
+}
+
+pub fn user_agent() -> String {
+    format!("agent-mana/{} ({})", env!("CARGO_PKG_VERSION"), std::env::consts::OS)
+}
+
+pub fn build_client(settings: &HttpSettings) -> Result<reqwest::Client, String> {
+    if settings.connect_timeout_secs == 0 || settings.read_timeout_secs == 0 || settings.request_timeout_secs == 0 {
+        return Err("Timeouts must be at least one second".to_string());
+    }
+
+    let mut builder = reqwest::Client::builder()
+        .user_agent(user_agent())
+        .connect_timeout(Duration::from_secs(settings.connect_timeout_secs))
+        .read_timeout(Duration::from_secs(settings.read_timeout_secs))
+        .timeout(Duration::from_secs(settings.request_timeout_secs));
+
+    if let Some(url) = settings.proxy_url.as_deref().filter(|u| !u.trim().is_empty()) {
+        let proxy = reqwest::Proxy::all(url)
+            .map_err(|e| format!("Invalid proxy URL '{}': {}", url, e))?
+            .no_proxy(settings.no_proxy.as_deref().and_then(reqwest::NoProxy::from_string));
+        builder = builder.proxy(proxy);
+    }
+
+    for path in &settings.ca_cert_paths {
+        let pem = std::fs::read(path).map_err(|e| format!("Failed to read CA certificate '{}': {}", path, e))?;
+        let certs = reqwest::Certificate::from_pem_bundle(&pem)
+            .map_err(|e| format!("Invalid CA certificate '{}': {}", path, e))?;
+        for cert in certs {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+
+    builder.build().map_err(|e| format!("Failed to build HTTP client: {}", e))
+}
+
+/// A client built from default settings, for callers with no [`HttpClient`] at hand.
+pub fn default_client() -> reqwest::Client {
+    DEFAULT_CLIENT
+        .get_or_init(|| {
+            build_client(&HttpSettings::default()).unwrap_or_else(|e| {
+                eprintln!("Failed to build HTTP client, falling back to reqwest defaults: {}", e);
+                reqwest::Client::new()
+            })
+        })
+        .clone()
+}
+
+/// The application-wide client. Cloning is cheap and every clone sees
+/// reconfiguration; `reqwest::Client`s handed out earlier keep their old settings.
+#[derive(Clone)]
+pub struct HttpClient {
+    inner: Arc<RwLock<(HttpSettings, reqwest::Client)>>,
+}
+
+impl Default for HttpClient {
+    fn default() -> Self {
+        Self {
+            inner: Arc::new(RwLock::new((HttpSettings::default(), default_client()))),
+        }
+    }
+}
+
+impl HttpClient {
+    pub fn client(&self) -> reqwest::Client {
+        self.inner.read().unwrap().1.clone()
+    }
+
+    pub fn settings(&self) -> HttpSettings {
+        self.inner.read().unwrap().0.clone()
+    }
+
+    /// Builds a client from `settings` and swaps it in; on error the current one stays.
+    pub fn reconfigure(&self, settings: HttpSettings) -> Result<(), String> {
+        let client = build_client(&settings)?;
+        *self.inner.write().unwrap() = (settings, client);
+        Ok(())
+    }
+}
+
+/// Hosts probed to tell a network outage apart from a failing provider.
+const PROBE_URLS: [&str; 2] = ["https://api.anthropic.com", "https://api.openai.com"];
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Whether any provider API can be reached. Any HTTP response counts, even an error status.
+pub async fn is_online(client: &reqwest::Client) -> bool {
+    let probe = |url: &'static str| client.head(url).timeout(PROBE_TIMEOUT).send();
+    let (anthropic, openai) = tokio::join!(probe(PROBE_URLS[0]), probe(PROBE_URLS[1]));
+    anthropic.is_ok() || openai.is_ok()
+}
+
+/// Parses a `Retry-After` header given either in seconds or as an HTTP date.
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    (at.with_timezone(&chrono::Utc) - chrono::Utc::now()).to_std().ok()
+}
@@ -1,6 +1,7 @@
 pub mod anthropic;
 pub mod claude_oauth;
 pub mod gemini;
+pub mod http;
 pub mod openai;
 
 pub use anthropic::{
@@ -22,7 +22,9 @@ pub enum OpenAIError {
     /// Authentication failed
     AuthenticationFailed,
     /// Rate limit exceeded
-    RateLimitExceeded,
+    RateLimitExceeded { retry_after: Option<std::time::Duration> },
+    /// Service temporarily unavailable (HTTP 503)
+    ServiceUnavailable { retry_after: Option<std::time::Duration> },
 }
 
 impl fmt::Display for OpenAIError {
@@ -35,7 +37,20 @@ impl fmt::Display for OpenAIError {
             OpenAIError::ParseError(msg) => write!(f, "Parse error: {}", msg),
             OpenAIError::InvalidApiKey => write!(f, "Invalid API key format"),
             OpenAIError::AuthenticationFailed => write!(f, "Authentication failed"),
-            OpenAIError::RateLimitExceeded => write!(f, "Rate limit exceeded"),
+            OpenAIError::RateLimitExceeded { .. } => write!(f, "Rate limit exceeded"),
+            OpenAIError::ServiceUnavailable { .. } => write!(f, "Service temporarily unavailable"),
+        }
+    }
+}
+
+impl OpenAIError {
+    /// How long the server asked us to wait before trying again, if it said.
+    pub fn retry_after(&self) -> Option<std::time::Duration> {
+        match self {
+            OpenAIError::RateLimitExceeded { retry_after } | OpenAIError::ServiceUnavailable { retry_after } => {
+                *retry_after
+            }
+            _ => None,
         }
     }
 }
@@ -284,7 +299,11 @@ impl OpenAIClient {
     /// let client = OpenAIClient::new("sk-...".to_string());
     /// ```
     pub fn new(api_key: String) -> Self {
-        let client = reqwest::Client::new();
+        Self::with_client(api_key, crate::api::http::default_client())
+    }
+
+    /// Create a client that reuses an existing `reqwest::Client`
+    pub fn with_client(api_key: String, client: reqwest::Client) -> Self {
         Self { api_key, client }
     }
 
@@ -353,7 +372,9 @@ impl OpenAIClient {
                 message: "This API key does not have admin permissions. Please create an Admin API key from platform.openai.com/settings with 'All' or 'Read' permissions for organization usage data.".to_string(),
             })
         } else if status.as_u16() == 429 {
-            Err(OpenAIError::RateLimitExceeded)
+            Err(OpenAIError::RateLimitExceeded {
+                retry_after: crate::api::http::retry_after(response.headers()),
+            })
         } else {
             let error_body = response.text().await.unwrap_or_default();
 
@@ -407,7 +428,9 @@ impl OpenAIClient {
         } else if status.as_u16() == 401 {
             Err(OpenAIError::AuthenticationFailed)
         } else if status.as_u16() == 429 {
-            Err(OpenAIError::RateLimitExceeded)
+            Err(OpenAIError::RateLimitExceeded {
+                retry_after: crate::api::http::retry_after(response.headers()),
+            })
         } else {
             let error_body = response.text().await.unwrap_or_default();
 
@@ -455,6 +478,16 @@ impl OpenAIClient {
                 message: "Admin API key required for usage data".to_string(),
             });
         }
+        if status.as_u16() == 429 {
+            return Err(OpenAIError::RateLimitExceeded {
+                retry_after: crate::api::http::retry_after(response.headers()),
+            });
+        }
+        if status.as_u16() == 503 {
+            return Err(OpenAIError::ServiceUnavailable {
+                retry_after: crate::api::http::retry_after(response.headers()),
+            });
+        }
         if !status.is_success() {
             let error_body = response.text().await.unwrap_or_default();
             return Err(OpenAIError::ApiError {
@@ -570,10 +603,11 @@ impl OpenAIClient {
 
         let status = response.status();
         if !status.is_success() {
+            let retry_after = crate::api::http::retry_after(response.headers());
             let error_text = response.text().await.unwrap_or_default();
             
             if status.as_u16() == 429 {
-                return Err(OpenAIError::RateLimitExceeded);
+                return Err(OpenAIError::RateLimitExceeded { retry_after });
             }
             
             return Err(OpenAIError::ApiError {
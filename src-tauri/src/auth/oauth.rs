@@ -4,12 +4,19 @@ use serde::{Deserialize, Serialize};
 use sha2::{Sha256, Digest};
 use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
 use tokio::sync::oneshot;
+use tokio_util::sync::CancellationToken;
 
 const CLAUDE_AUTH_URL: &str = "https://claude.ai/oauth/authorize";
 const CLAUDE_TOKEN_URL: &str = "https://console.anthropic.com/v1/oauth/token";
 const CLAUDE_CLIENT_ID: &str = "9d1c250a-e61b-44d9-88ed-5944d1962f5e";
 const REDIRECT_PORT: u16 = 19832;
+const FALLBACK_PORTS: [u16; 3] = [19833, 19834, 19835];
+const CALLBACK_TIMEOUT: Duration = Duration::from_secs(300);
+/// Hosted page that displays the authorization code for the user to copy.
+const MANUAL_REDIRECT_URI: &str = "https://console.anthropic.com/oauth/code/callback";
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct OAuthTokenResponse {
@@ -21,10 +28,34 @@ pub struct OAuthTokenResponse {
     pub scope: Option<String>,
 }
 
+pub struct AuthorizationRequest {
+    pub url: String,
+    pub code_verifier: String,
+    pub state: String,
+    pub redirect_uri: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "stage", rename_all = "snake_case")]
+pub enum OAuthProgress {
+    ServerStarted { port: u16 },
+    BrowserOpened { url: String },
+    CallbackReceived,
+    ExchangingCode,
+    Completed,
+    Failed { message: String },
+    Cancelled,
+}
+
 #[derive(Debug)]
 pub enum OAuthError {
     PkceGeneration,
     ServerStart(String),
+    BrowserOpen(String),
+    StateMismatch,
+    Provider(String),
+    /// A pasted code or URL that doesn't contain a usable authorization code.
+    InvalidCode(String),
     TokenExchange(String),
     Timeout,
     Cancelled,
@@ -35,6 +66,10 @@ impl std::fmt::Display for OAuthError {
         match self {
             Self::PkceGeneration => write!(f, "Failed to generate PKCE codes"),
             Self::ServerStart(e) => write!(f, "Failed to start callback server: {}", e),
+            Self::BrowserOpen(e) => write!(f, "Failed to open browser: {}", e),
+            Self::StateMismatch => write!(f, "OAuth callback state did not match the request"),
+            Self::Provider(e) => write!(f, "Authorization was denied: {}", e),
+            Self::InvalidCode(e) => write!(f, "Invalid authorization code: {}", e),
             Self::TokenExchange(e) => write!(f, "Token exchange failed: {}", e),
             Self::Timeout => write!(f, "OAuth flow timed out"),
             Self::Cancelled => write!(f, "OAuth flow cancelled"),
@@ -44,15 +79,42 @@ impl std::fmt::Display for OAuthError {
 
 impl std::error::Error for OAuthError {}
 
+#[derive(Deserialize)]
+struct CallbackParams {
+    code: Option<String>,
+    state: Option<String>,
+    error: Option<String>,
+    error_description: Option<String>,
+}
+
+type CallbackSender = Arc<tokio::sync::Mutex<Option<oneshot::Sender<Result<String, OAuthError>>>>>;
+
+fn callback_page(title: &str, message: &str) -> String {
+    format!(
+        "<html><body style='background:#1a1a1a;color:#fff;font-family:system-ui;display:flex;align-items:center;justify-content:center;height:100vh;margin:0'><div style='text-align:center'><h1>{}</h1><p>{}</p></div></body></html>",
+        title, escape_html(message)
+    )
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
 pub struct OAuthManager {
     client: reqwest::Client,
 }
 
 impl OAuthManager {
     pub fn new() -> Self {
-        Self {
-            client: reqwest::Client::new(),
-        }
+        Self::with_client(crate::api::http::default_client())
+    }
+
+    pub fn with_client(client: reqwest::Client) -> Self {
+        Self { client }
     }
 
     fn generate_code_verifier() -> String {
@@ -68,29 +130,36 @@ impl OAuthManager {
         URL_SAFE_NO_PAD.encode(&hash)
     }
 
-    pub fn get_authorization_url(&self) -> (String, String, String) {
+    pub fn authorization_request(&self, redirect_uri: &str) -> AuthorizationRequest {
         let code_verifier = Self::generate_code_verifier();
         let code_challenge = Self::generate_code_challenge(&code_verifier);
         let state = Self::generate_code_verifier();
         
-        let redirect_uri = format!("http://localhost:{}/callback", REDIRECT_PORT);
-        
         let url = format!(
             "{}?client_id={}&response_type=code&code_challenge={}&code_challenge_method=S256&redirect_uri={}&scope={}&state={}",
             CLAUDE_AUTH_URL,
             CLAUDE_CLIENT_ID,
             code_challenge,
-            urlencoding::encode(&redirect_uri),
+            urlencoding::encode(redirect_uri),
             urlencoding::encode("user:inference user:profile"),
             state
         );
         
-        (url, code_verifier, state)
+        AuthorizationRequest {
+            url,
+            code_verifier,
+            state,
+            redirect_uri: redirect_uri.to_string(),
+        }
     }
 
-    pub async fn exchange_code(&self, code: &str, code_verifier: &str, state: &str) -> Result<OAuthTokenResponse, OAuthError> {
-        let redirect_uri = format!("http://localhost:{}/callback", REDIRECT_PORT);
-        
+    pub async fn exchange_code(
+        &self,
+        code: &str,
+        code_verifier: &str,
+        state: &str,
+        redirect_uri: &str,
+    ) -> Result<OAuthTokenResponse, OAuthError> {
         let body = serde_json::json!({
             "grant_type": "authorization_code",
             "client_id": CLAUDE_CLIENT_ID,
@@ -117,58 +186,201 @@ impl OAuthManager {
             .map_err(|e| OAuthError::TokenExchange(e.to_string()))
     }
 
-    pub async fn start_oauth_flow(&self) -> Result<OAuthTokenResponse, OAuthError> {
-        let (auth_url, code_verifier, state) = self.get_authorization_url();
-        
-        let (tx, rx) = oneshot::channel::<String>();
-        let tx = Arc::new(tokio::sync::Mutex::new(Some(tx)));
-        
-        let tx_clone = Arc::clone(&tx);
-        let server = tokio::spawn(async move {
-            use axum::{Router, routing::get, extract::Query, response::Html};
-            
-            #[derive(Deserialize)]
-            struct CallbackParams {
-                code: Option<String>,
-                error: Option<String>,
+    /// Builds a request for the paste-the-code flow, where the provider shows the
+    /// code on a hosted page instead of redirecting to a local server.
+    pub fn manual_authorization_request(&self) -> AuthorizationRequest {
+        let mut request = self.authorization_request(MANUAL_REDIRECT_URI);
+        request.url.push_str("&code=true");
+        request
+    }
+
+    /// Exchanges a code pasted by the user. Accepts the `code#state` string shown
+    /// on the hosted page as well as a full callback URL.
+    pub async fn exchange_pasted_code(
+        &self,
+        pasted: &str,
+        request: &AuthorizationRequest,
+    ) -> Result<OAuthTokenResponse, OAuthError> {
+        let (code, state) = Self::parse_pasted_code(pasted)?;
+        if let Some(state) = state {
+            if state != request.state {
+                return Err(OAuthError::StateMismatch);
+            }
+        }
+
+        self.exchange_code(&code, &request.code_verifier, &request.state, &request.redirect_uri).await
+    }
+
+    fn parse_pasted_code(pasted: &str) -> Result<(String, Option<String>), OAuthError> {
+        let pasted = pasted.trim();
+
+        if let Some((_, query)) = pasted.split_once('?') {
+            let mut code = None;
+            let mut state = None;
+            for pair in query.split(['&', '#']) {
+                let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+                let value = urlencoding::decode(value)
+                    .map(|v| v.into_owned())
+                    .unwrap_or_else(|_| value.to_string());
+                match key {
+                    "code" => code = Some(value),
+                    "state" => state = Some(value),
+                    "error" => return Err(OAuthError::Provider(value)),
+                    _ => {}
+                }
             }
-            
-            let tx_inner = tx_clone;
-            
-            let app = Router::new().route("/callback", get(move |Query(params): Query<CallbackParams>| {
-                let tx = Arc::clone(&tx_inner);
-                async move {
-                    if let Some(code) = params.code {
-                        if let Some(sender) = tx.lock().await.take() {
-                            let _ = sender.send(code);
-                        }
-                        Html("<html><body style='background:#1a1a1a;color:#fff;font-family:system-ui;display:flex;align-items:center;justify-content:center;height:100vh;margin:0'><div style='text-align:center'><h1>Connected!</h1><p>You can close this window and return to Agent Mana.</p></div></body></html>")
-                    } else {
-                        Html("<html><body style='background:#1a1a1a;color:#fff;font-family:system-ui;display:flex;align-items:center;justify-content:center;height:100vh;margin:0'><div style='text-align:center'><h1>Error</h1><p>Authentication failed. Please try again.</p></div></body></html>")
+            return code
+                .filter(|c| !c.is_empty())
+                .map(|c| (c, state))
+                .ok_or_else(|| OAuthError::InvalidCode("no code in the pasted URL".to_string()));
+        }
+
+        let (code, state) = match pasted.split_once('#') {
+            Some((code, state)) => (code, Some(state.to_string())),
+            None => (pasted, None),
+        };
+        if code.is_empty() {
+            return Err(OAuthError::InvalidCode("the code is empty".to_string()));
+        }
+        Ok((code.to_string(), state))
+    }
+
+    /// Runs the loopback flow, reporting each step through `progress`.
+    ///
+    /// Cancelling `cancel` stops the callback server and ends the flow with
+    /// [`OAuthError::Cancelled`].
+    pub async fn start_oauth_flow<F>(
+        &self,
+        cancel: CancellationToken,
+        progress: F,
+    ) -> Result<OAuthTokenResponse, OAuthError>
+    where
+        F: Fn(OAuthProgress),
+    {
+        let result = self.run_loopback_flow(&cancel, &progress).await;
+        match &result {
+            Ok(_) => progress(OAuthProgress::Completed),
+            Err(OAuthError::Cancelled) => progress(OAuthProgress::Cancelled),
+            Err(e) => progress(OAuthProgress::Failed { message: e.to_string() }),
+        }
+        result
+    }
+
+    async fn run_loopback_flow<F>(
+        &self,
+        cancel: &CancellationToken,
+        progress: &F,
+    ) -> Result<OAuthTokenResponse, OAuthError>
+    where
+        F: Fn(OAuthProgress),
+    {
+        let listener = Self::bind_callback_listener().await?;
+        let port = listener
+            .local_addr()
+            .map_err(|e| OAuthError::ServerStart(e.to_string()))?
+            .port();
+        progress(OAuthProgress::ServerStarted { port });
+
+        let redirect_uri = format!("http://localhost:{}/callback", port);
+        let request = self.authorization_request(&redirect_uri);
+
+        let (tx, rx) = oneshot::channel();
+        let tx: CallbackSender = Arc::new(tokio::sync::Mutex::new(Some(tx)));
+
+        let server_token = cancel.child_token();
+        let server = Self::spawn_callback_server(listener, request.state.clone(), tx, server_token.clone());
+
+        let code = async {
+            open::that(&request.url).map_err(|e| OAuthError::BrowserOpen(e.to_string()))?;
+            progress(OAuthProgress::BrowserOpened { url: request.url.clone() });
+
+            tokio::select! {
+                received = rx => received.map_err(|_| OAuthError::Cancelled)?,
+                _ = cancel.cancelled() => Err(OAuthError::Cancelled),
+                _ = tokio::time::sleep(CALLBACK_TIMEOUT) => Err(OAuthError::Timeout),
+            }
+        }.await;
+
+        // Graceful shutdown lets the success page finish sending before the server exits.
+        server_token.cancel();
+        drop(server);
+
+        let code = code?;
+        progress(OAuthProgress::CallbackReceived);
+        progress(OAuthProgress::ExchangingCode);
+
+        self.exchange_code(&code, &request.code_verifier, &request.state, &request.redirect_uri).await
+    }
+
+    async fn bind_callback_listener() -> Result<TcpListener, OAuthError> {
+        for port in std::iter::once(REDIRECT_PORT).chain(FALLBACK_PORTS) {
+            match TcpListener::bind(("127.0.0.1", port)).await {
+                Ok(listener) => return Ok(listener),
+                Err(e) => eprintln!("OAuth callback port {} unavailable: {}", port, e),
+            }
+        }
+
+        TcpListener::bind(("127.0.0.1", 0))
+            .await
+            .map_err(|e| OAuthError::ServerStart(e.to_string()))
+    }
+
+    fn spawn_callback_server(
+        listener: TcpListener,
+        expected_state: String,
+        tx: CallbackSender,
+        shutdown: CancellationToken,
+    ) -> tokio::task::JoinHandle<()> {
+        use axum::{Router, routing::get, extract::Query, response::Html};
+
+        let app = Router::new().route("/callback", get(move |Query(params): Query<CallbackParams>| {
+            let tx = Arc::clone(&tx);
+            let expected_state = expected_state.clone();
+            async move {
+                let (outcome, page) = Self::evaluate_callback(params, &expected_state);
+                if let Some(outcome) = outcome {
+                    if let Some(sender) = tx.lock().await.take() {
+                        let _ = sender.send(outcome);
                     }
                 }
-            }));
-            
-            let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", REDIRECT_PORT))
-                .await
-                .map_err(|e| OAuthError::ServerStart(e.to_string()))?;
-            
-            axum::serve(listener, app)
+                Html(page)
+            }
+        }));
+
+        tokio::spawn(async move {
+            if let Err(e) = axum::serve(listener, app)
+                .with_graceful_shutdown(shutdown.cancelled_owned())
                 .await
-                .map_err(|e| OAuthError::ServerStart(e.to_string()))
-        });
-        
-        open::that(&auth_url).map_err(|e| OAuthError::ServerStart(e.to_string()))?;
-        
-        let code = tokio::time::timeout(
-            std::time::Duration::from_secs(300),
-            rx
-        ).await
-            .map_err(|_| OAuthError::Timeout)?
-            .map_err(|_| OAuthError::Cancelled)?;
-        
-        server.abort();
-        
-        self.exchange_code(&code, &code_verifier, &state).await
+            {
+                eprintln!("OAuth callback server error: {}", e);
+            }
+        })
+    }
+
+    /// Returns the page to answer with and, for a callback that settles the
+    /// flow, its outcome. Requests without this flow's `state` get an error page
+    /// but leave the flow waiting, so a stray or forged local request can't end it.
+    fn evaluate_callback(
+        params: CallbackParams,
+        expected_state: &str,
+    ) -> (Option<Result<String, OAuthError>>, String) {
+        if params.state.as_deref() != Some(expected_state) {
+            let page = callback_page("Error", "Authentication response did not match this sign-in attempt.");
+            return (None, page);
+        }
+
+        if let Some(error) = params.error {
+            let message = params.error_description.unwrap_or(error);
+            let page = callback_page("Error", &format!("Authentication failed: {}", message));
+            return (Some(Err(OAuthError::Provider(message))), page);
+        }
+
+        match params.code.filter(|c| !c.is_empty()) {
+            Some(code) => (
+                Some(Ok(code)),
+                callback_page("Connected!", "You can close this window and return to Agent Mana."),
+            ),
+            None => (None, callback_page("Error", "No authorization code was returned.")),
+        }
     }
 }
@@ -1,11 +1,20 @@
-use crate::services::{ProviderStatus, NotificationService, NotificationThreshold, ClaudeUsageSnapshot};
+use crate::services::{
+    ProviderStatus, NotificationService, NotificationThreshold, ClaudeUsageSnapshot, ResetNotificationSettings,
+    AlertMessage, AlertSeverity, AlertRule, NotificationSink, QuietHours,
+};
 use crate::services::notification::show_app_notification;
+use crate::services::history::NotificationRecord;
+use crate::services::polling::{emit_status_changed, PollSettings, PollingHandle, PollingService};
+use crate::services::power::PowerState;
 use crate::storage::keychain::KeychainManager;
-use crate::auth::oauth::OAuthManager;
-use crate::api::claude_oauth::ClaudeOAuthClient;
-use tauri::State;
+use crate::auth::oauth::{AuthorizationRequest, OAuthError, OAuthManager, OAuthTokenResponse};
+use crate::api::claude_oauth::{ClaudeOAuthClient, RefreshPolicy, TokenStorage};
+use crate::api::http::{HttpClient, HttpSettings};
+use crate::storage::settings;
+use tauri::{Emitter, State};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{Mutex, RwLock};
+use tokio_util::sync::CancellationToken;
 
 pub struct AppState {
     pub keychain: KeychainManager,
@@ -13,6 +22,13 @@ pub struct AppState {
     pub openai_status: Arc<RwLock<ProviderStatus>>,
     pub gemini_status: Arc<RwLock<ProviderStatus>>,
     pub notification_service: Arc<RwLock<NotificationService>>,
+    pub oauth_flow: Arc<Mutex<Option<CancellationToken>>>,
+    pub pending_oauth: Arc<Mutex<Option<AuthorizationRequest>>>,
+    pub polling: PollingHandle,
+    pub http: HttpClient,
+    /// Cancelled on quit; background tasks and local servers stop when it is.
+    pub shutdown: CancellationToken,
+    pub polling_task: Arc<Mutex<Option<tauri::async_runtime::JoinHandle<()>>>>,
 }
 
 #[tauri::command]
@@ -28,8 +44,26 @@ pub async fn get_provider_status(
     }
 }
 
+/// Polls one provider immediately and returns its updated status.
+#[tauri::command]
+pub async fn refresh_provider(provider: String, state: State<'_, AppState>) -> Result<ProviderStatus, String> {
+    state.polling.refresh(&provider).await?;
+    get_provider_status(provider, state).await
+}
+
+#[tauri::command]
+pub async fn refresh_all(state: State<'_, AppState>) -> Result<Vec<ProviderStatus>, String> {
+    state.polling.refresh_all().await?;
+    Ok(vec![
+        state.anthropic_status.read().await.clone(),
+        state.openai_status.read().await.clone(),
+        state.gemini_status.read().await.clone(),
+    ])
+}
+
 #[tauri::command]
 pub async fn save_api_key(
+    app: tauri::AppHandle,
     provider: String,
     api_key: String,
     state: State<'_, AppState>,
@@ -42,7 +76,7 @@ pub async fn save_api_key(
         use crate::api::openai::OpenAIClient;
         use crate::services::CodexUsageSnapshot;
         
-        let client = OpenAIClient::new(api_key);
+        let client = OpenAIClient::with_client(api_key, state.http.client());
         
         match client.validate_key().await {
             Ok(true) => {
@@ -85,10 +119,12 @@ pub async fn save_api_key(
                 status.error = Some(format!("Error: {}", e));
             }
         }
+        // The schedule may have slowed down while disconnected.
+        state.polling.wake();
     } else if provider == "google" {
         use crate::api::gemini::GeminiClient;
         
-        let client = GeminiClient::new(api_key);
+        let client = GeminiClient::with_client(api_key, state.http.client());
         
         match client.validate_key().await {
             Ok(true) => {
@@ -113,11 +149,13 @@ pub async fn save_api_key(
         }
     }
     
+    emit_key_status(&app, &provider, &state).await;
     Ok(())
 }
 
 #[tauri::command]
 pub async fn remove_api_key(
+    app: tauri::AppHandle,
     provider: String,
     state: State<'_, AppState>,
 ) -> Result<(), String> {
@@ -138,21 +176,254 @@ pub async fn remove_api_key(
         status.error = None;
     }
     
+    emit_key_status(&app, &provider, &state).await;
     Ok(())
 }
 
+/// Pushes a status the key commands just changed, since the poller only emits its own.
+async fn emit_key_status(app: &tauri::AppHandle, provider: &str, state: &AppState) {
+    let status = match provider {
+        "openai" => &state.openai_status,
+        "google" => &state.gemini_status,
+        _ => return,
+    };
+    emit_status_changed(app, &*status.read().await);
+}
+
 #[tauri::command]
 pub async fn save_threshold(
+    app: tauri::AppHandle,
     threshold: NotificationThreshold,
     state: State<'_, AppState>,
+) -> Result<String, String> {
+    let mut service = state.notification_service.write().await;
+    let id = service.add_threshold(threshold);
+    service.persist(&app)?;
+    Ok(id)
+}
+
+#[tauri::command]
+pub async fn list_thresholds(
+    state: State<'_, AppState>,
+) -> Result<Vec<NotificationThreshold>, String> {
+    Ok(state.notification_service.read().await.thresholds().to_vec())
+}
+
+#[tauri::command]
+pub async fn delete_threshold(
+    app: tauri::AppHandle,
+    id: String,
+    state: State<'_, AppState>,
+) -> Result<bool, String> {
+    let mut service = state.notification_service.write().await;
+    let removed = service.remove_threshold(&id);
+    service.persist(&app)?;
+    Ok(removed)
+}
+
+#[tauri::command]
+pub async fn save_alert_rule(
+    app: tauri::AppHandle,
+    rule: AlertRule,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    let mut service = state.notification_service.write().await;
+    let id = service.save_rule(rule)?;
+    service.persist(&app)?;
+    Ok(id)
+}
+
+#[tauri::command]
+pub async fn list_alert_rules(state: State<'_, AppState>) -> Result<Vec<AlertRule>, String> {
+    Ok(state.notification_service.read().await.rules().to_vec())
+}
+
+#[tauri::command]
+pub async fn delete_alert_rule(
+    app: tauri::AppHandle,
+    id: String,
+    state: State<'_, AppState>,
+) -> Result<bool, String> {
+    let mut service = state.notification_service.write().await;
+    let removed = service.remove_rule(&id);
+    service.persist(&app)?;
+    Ok(removed)
+}
+
+#[tauri::command]
+pub async fn get_reset_notifications(
+    state: State<'_, AppState>,
+) -> Result<ResetNotificationSettings, String> {
+    Ok(state.notification_service.read().await.reset_settings().clone())
+}
+
+#[tauri::command]
+pub async fn set_reset_notifications(
+    app: tauri::AppHandle,
+    reset_settings: ResetNotificationSettings,
+    state: State<'_, AppState>,
 ) -> Result<(), String> {
     let mut service = state.notification_service.write().await;
-    service.add_threshold(threshold);
+    service.set_reset_settings(reset_settings);
+    service.persist(&app)
+}
+
+#[tauri::command]
+pub async fn list_sinks(state: State<'_, AppState>) -> Result<Vec<NotificationSink>, String> {
+    Ok(state.notification_service.read().await.sinks().to_vec())
+}
+
+#[tauri::command]
+pub async fn save_sink(
+    app: tauri::AppHandle,
+    sink: NotificationSink,
+    smtp_password: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    let mut service = state.notification_service.write().await;
+    let id = service.save_sink(sink)?;
+    if let Some(password) = smtp_password {
+        state
+            .keychain
+            .store_smtp_password(&id, &password)
+            .map_err(|e| format!("Failed to store SMTP password: {}", e))?;
+    }
+    service.persist(&app)?;
+    Ok(id)
+}
+
+#[tauri::command]
+pub async fn delete_sink(
+    app: tauri::AppHandle,
+    id: String,
+    state: State<'_, AppState>,
+) -> Result<bool, String> {
+    let mut service = state.notification_service.write().await;
+    let removed = service.remove_sink(&id);
+    if let Err(e) = state.keychain.delete_smtp_password(&id) {
+        eprintln!("Failed to delete SMTP password for sink '{}': {}", id, e);
+    }
+    service.persist(&app)?;
+    Ok(removed)
+}
+
+/// Sends a sample alert to one sink and reports the delivery result.
+#[tauri::command]
+pub async fn test_sink(id: String, state: State<'_, AppState>) -> Result<(), String> {
+    let sink = state.notification_service.read().await
+        .sink(&id)
+        .cloned()
+        .ok_or("Unknown sink")?;
+    
+    let alert = AlertMessage::new(
+        "Agent Mana - Test",
+        format!("Sink '{}' is working", sink.name),
+        "agent-mana",
+        AlertSeverity::Info,
+    );
+    sink.deliver(&state.http.client(), &alert).await
+}
+
+#[tauri::command]
+pub async fn get_poll_settings(state: State<'_, AppState>) -> Result<PollSettings, String> {
+    Ok(state.polling.settings.read().await.clone())
+}
+
+#[tauri::command]
+pub async fn set_poll_settings(
+    app: tauri::AppHandle,
+    poll_settings: PollSettings,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    poll_settings.validate()?;
+    settings::save(&app, settings::POLL_SETTINGS, &poll_settings)?;
+    *state.polling.settings.write().await = poll_settings;
+    state.polling.wake();
     Ok(())
 }
 
+#[tauri::command]
+pub async fn get_power_state(state: State<'_, AppState>) -> Result<PowerState, String> {
+    Ok(state.polling.power_state().await)
+}
+
+#[tauri::command]
+pub async fn get_http_settings(state: State<'_, AppState>) -> Result<HttpSettings, String> {
+    Ok(state.http.settings())
+}
+
+/// Applies to requests started after the call; requests in flight finish as they are.
+#[tauri::command]
+pub async fn set_http_settings(
+    app: tauri::AppHandle,
+    http_settings: HttpSettings,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    state.http.reconfigure(http_settings.clone())?;
+    settings::save(&app, settings::HTTP_SETTINGS, &http_settings)
+}
+
+#[tauri::command]
+pub async fn get_quiet_hours(state: State<'_, AppState>) -> Result<QuietHours, String> {
+    Ok(state.notification_service.read().await.quiet_hours().clone())
+}
+
+#[tauri::command]
+pub async fn set_quiet_hours(
+    app: tauri::AppHandle,
+    quiet_hours: QuietHours,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let mut service = state.notification_service.write().await;
+    service.set_quiet_hours(quiet_hours)?;
+    service.persist(&app)
+}
+
+#[tauri::command]
+pub async fn list_notification_history(
+    limit: Option<usize>,
+    unacknowledged_only: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<Vec<NotificationRecord>, String> {
+    let history = state.notification_service.read().await.history();
+    let history = history.lock().map_err(|e| e.to_string())?;
+    Ok(history.list(limit, unacknowledged_only.unwrap_or(false)))
+}
+
+#[tauri::command]
+pub async fn acknowledge_notification(
+    app: tauri::AppHandle,
+    id: u64,
+    state: State<'_, AppState>,
+) -> Result<bool, String> {
+    let history = state.notification_service.read().await.history();
+    let mut history = history.lock().map_err(|e| e.to_string())?;
+    let found = history.acknowledge(id);
+    if found {
+        history.persist(&app)?;
+    }
+    Ok(found)
+}
+
+#[tauri::command]
+pub async fn acknowledge_all_notifications(app: tauri::AppHandle, state: State<'_, AppState>) -> Result<(), String> {
+    let history = state.notification_service.read().await.history();
+    let mut history = history.lock().map_err(|e| e.to_string())?;
+    history.acknowledge_all();
+    history.persist(&app)
+}
+
+#[tauri::command]
+pub async fn clear_notification_history(app: tauri::AppHandle, state: State<'_, AppState>) -> Result<(), String> {
+    let history = state.notification_service.read().await.history();
+    let mut history = history.lock().map_err(|e| e.to_string())?;
+    history.clear();
+    history.persist(&app)
+}
+
 #[tauri::command]
 pub async fn start_oauth_flow(
+    app: tauri::AppHandle,
     provider: String,
     state: State<'_, AppState>,
 ) -> Result<(), String> {
@@ -160,8 +431,8 @@ pub async fn start_oauth_flow(
         return Err("OAuth only supported for Anthropic".to_string());
     }
 
-    let should_start_oauth = if ClaudeOAuthClient::has_credentials() {
-        match ClaudeOAuthClient::from_credentials_file_with_refresh().await {
+    let should_start_oauth = if ClaudeOAuthClient::has_credentials().await {
+        match ClaudeOAuthClient::from_credentials_file_with_refresh(&state.http.client()).await {
             Ok(client) => {
                 match client.get_usage().await {
                     Ok(usage) => {
@@ -183,6 +454,7 @@ pub async fn start_oauth_flow(
                                 .as_ref()
                                 .map(|u| u.resets_at.clone()),
                         });
+                        emit_status_changed(&app, &status);
                         return Ok(());
                     }
                     Err(_) => true,
@@ -198,36 +470,89 @@ pub async fn start_oauth_flow(
         return Ok(());
     }
 
-    let oauth = OAuthManager::new();
-    let tokens = oauth.start_oauth_flow().await
-        .map_err(|e| e.to_string())?;
+    let cancel = state.shutdown.child_token();
+    if let Some(previous) = state.oauth_flow.lock().await.replace(cancel.clone()) {
+        previous.cancel();
+    }
     
-    let creds_path = dirs::home_dir()
-        .ok_or("Could not find home directory")?
-        .join(".claude")
-        .join(".credentials.json");
+    let oauth = OAuthManager::with_client(state.http.client());
+    let result = oauth
+        .start_oauth_flow(cancel.clone(), |progress| {
+            let _ = app.emit("oauth-progress", progress);
+        })
+        .await;
     
-    std::fs::create_dir_all(creds_path.parent().unwrap())
-        .map_err(|e| e.to_string())?;
+    // A cancelled token has already been taken out of the slot, either by
+    // `cancel_oauth_flow` or by a newer flow replacing it.
+    if !cancel.is_cancelled() {
+        state.oauth_flow.lock().await.take();
+    }
     
-    let now_ms = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_millis() as u64;
+    let tokens = result.map_err(|e| e.to_string())?;
+    
+    complete_oauth_login(&app, tokens, &state).await
+}
+
+#[tauri::command]
+pub async fn begin_oauth_paste_flow(
+    provider: String,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    if provider != "anthropic" {
+        return Err("OAuth only supported for Anthropic".to_string());
+    }
+
+    let request = OAuthManager::with_client(state.http.client()).manual_authorization_request();
+    let url = request.url.clone();
+    *state.pending_oauth.lock().await = Some(request);
     
-    let creds = serde_json::json!({
-        "claudeAiOauth": {
-            "accessToken": tokens.access_token,
-            "refreshToken": tokens.refresh_token,
-            "expiresAt": now_ms + (tokens.expires_in * 1000),
-            "scopes": tokens.scope.unwrap_or_default().split(' ').collect::<Vec<_>>(),
+    eprintln!("Open this URL to authorize Agent Mana, then paste the code shown:\n{}", url);
+    Ok(url)
+}
+
+#[tauri::command]
+pub async fn submit_oauth_code(
+    app: tauri::AppHandle,
+    code: String,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let request = state.pending_oauth.lock().await.take()
+        .ok_or("No pending sign-in. Start the paste-the-code flow first.")?;
+    
+    let oauth = OAuthManager::with_client(state.http.client());
+    let tokens = match oauth.exchange_pasted_code(&code, &request).await {
+        Ok(tokens) => tokens,
+        Err(e) => {
+            // A mistyped or mismatched code was never redeemed, so the user may retry.
+            if matches!(e, OAuthError::StateMismatch | OAuthError::Provider(_) | OAuthError::InvalidCode(_)) {
+                state.pending_oauth.lock().await.get_or_insert(request);
+            }
+            return Err(e.to_string());
         }
-    });
+    };
     
-    std::fs::write(&creds_path, serde_json::to_string_pretty(&creds).unwrap())
-        .map_err(|e| e.to_string())?;
+    complete_oauth_login(&app, tokens, &state).await
+}
+
+async fn complete_oauth_login(
+    app: &tauri::AppHandle,
+    tokens: OAuthTokenResponse,
+    state: &AppState,
+) -> Result<(), String> {
+    let storage = settings::load(app, settings::OAUTH_TOKEN_STORAGE).unwrap_or_default();
+    let scopes = tokens.scope
+        .as_deref()
+        .map(|s| s.split_whitespace().map(String::from).collect());
+    let (access_token, refresh_token, expires_in) =
+        (tokens.access_token.clone(), tokens.refresh_token.clone(), tokens.expires_in);
+    tokio::task::spawn_blocking(move || {
+        ClaudeOAuthClient::store_tokens(&access_token, &refresh_token, expires_in, scopes, storage)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())?;
     
-    let client = ClaudeOAuthClient::new(tokens.access_token);
+    let client = ClaudeOAuthClient::with_client(tokens.access_token, state.http.client());
     let usage = client.get_usage().await.map_err(|e| e.to_string())?;
     
     let mut status = state.anthropic_status.write().await;
@@ -248,16 +573,65 @@ pub async fn start_oauth_flow(
             .as_ref()
             .map(|u| u.resets_at.clone()),
     });
+    emit_status_changed(app, &status);
     
+    drop(status);
+    state.polling.wake();
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_token_refresh_policy() -> Result<RefreshPolicy, String> {
+    Ok(ClaudeOAuthClient::refresh_policy())
+}
+
+#[tauri::command]
+pub async fn set_token_refresh_policy(
+    app: tauri::AppHandle,
+    policy: RefreshPolicy,
+) -> Result<(), String> {
+    policy.validate()?;
+    settings::save(&app, settings::TOKEN_REFRESH_POLICY, &policy)?;
+    ClaudeOAuthClient::set_refresh_policy(policy);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_oauth_token_storage(app: tauri::AppHandle) -> Result<TokenStorage, String> {
+    Ok(settings::load(&app, settings::OAUTH_TOKEN_STORAGE).unwrap_or_default())
+}
+
+/// New logins write to the chosen store, and the keychain is only read while it
+/// is selected. Switching to the keychain does not remove a refresh token an
+/// earlier login wrote to `~/.claude/.credentials.json`.
+#[tauri::command]
+pub async fn set_oauth_token_storage(
+    app: tauri::AppHandle,
+    storage: TokenStorage,
+) -> Result<(), String> {
+    settings::save(&app, settings::OAUTH_TOKEN_STORAGE, &storage)?;
+    ClaudeOAuthClient::set_token_storage(storage);
     Ok(())
 }
 
+#[tauri::command]
+pub async fn cancel_oauth_flow(state: State<'_, AppState>) -> Result<bool, String> {
+    match state.oauth_flow.lock().await.take() {
+        Some(token) => {
+            token.cancel();
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
 #[tauri::command]
 pub async fn check_oauth_credentials(provider: String) -> Result<bool, String> {
     if provider != "anthropic" {
         return Ok(false);
     }
-    Ok(ClaudeOAuthClient::has_credentials())
+    Ok(ClaudeOAuthClient::has_credentials().await)
 }
 
 #[tauri::command]
@@ -265,16 +639,22 @@ pub async fn update_tray_icon(
     app: tauri::AppHandle,
     weekly_remaining: f64,
     period_remaining: f64,
+    state: State<'_, AppState>,
 ) -> Result<(), String> {
-    use tauri::image::Image;
-    
-    let icon_data = crate::tray::generate_tray_icon(weekly_remaining, period_remaining);
-    let icon = Image::new_owned(icon_data, 22, 22);
-    
-    if let Some(tray) = app.tray_by_id("main") {
-        tray.set_icon(Some(icon)).map_err(|e| e.to_string())?;
-    }
-    
+    // Draw the way the poller does, so the Codex badge and the stale/offline
+    // dimming aren't painted over.
+    let dimmed = {
+        let status = state.anthropic_status.read().await;
+        status.stale || status.offline
+    };
+    let codex_connected = state.openai_status.read().await.connected;
+    PollingService::update_tray_connected(
+        100.0 - weekly_remaining,
+        100.0 - period_remaining,
+        codex_connected,
+        dimmed,
+        &app,
+    );
     Ok(())
 }
 
@@ -356,6 +736,8 @@ pub async fn update_tray_menu(
         .map_err(|e| e.to_string())?;
     let separator = PredefinedMenuItem::separator(&app)
         .map_err(|e| e.to_string())?;
+    let refresh_item = MenuItem::with_id(&app, "refresh", "Refresh Now", true, None::<&str>)
+        .map_err(|e| e.to_string())?;
     let show_item = MenuItem::with_id(&app, "show", "Show Window", true, None::<&str>)
         .map_err(|e| e.to_string())?;
     let quit_item = MenuItem::with_id(&app, "quit", "Quit Agent Mana", true, None::<&str>)
@@ -368,6 +750,7 @@ pub async fn update_tray_menu(
         &period_item,
         &period_reset_item,
         &separator,
+        &refresh_item,
         &show_item,
         &quit_item,
     ]).map_err(|e| e.to_string())?;
@@ -6,16 +6,54 @@ pub mod commands;
 pub mod tray;
 
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
+use tokio_util::sync::CancellationToken;
 use commands::AppState;
-use services::polling::PollingService;
+use services::polling::{PollSettings, PollingHandle, PollingService};
 use services::{ProviderStatus, NotificationService};
 use storage::keychain::KeychainManager;
+use storage::settings;
+use api::claude_oauth::{ClaudeOAuthClient, RefreshPolicy};
+use api::http::HttpClient;
 use tauri::tray::TrayIconBuilder;
 use tauri::menu::{Menu, MenuItem};
 use tauri::image::Image;
 use tauri::{Manager, WindowEvent};
 
+/// How long quitting waits for a poll in progress before exiting anyway.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Stops polling, sink deliveries and the OAuth callback server, then writes out
+/// persisted state. Only the first call does anything.
+async fn graceful_shutdown(app: &tauri::AppHandle) {
+    let state = app.state::<AppState>();
+    if state.shutdown.is_cancelled() {
+        return;
+    }
+    state.shutdown.cancel();
+
+    let polling_task = state.polling_task.lock().await.take();
+    if let Some(task) = polling_task {
+        if tokio::time::timeout(SHUTDOWN_TIMEOUT, task).await.is_err() {
+            eprintln!("Polling did not stop within {:?}, exiting anyway", SHUTDOWN_TIMEOUT);
+        }
+    }
+
+    let notifier = state.notification_service.read().await;
+    if let Err(e) = notifier.persist(app) {
+        eprintln!("Failed to persist notification settings: {}", e);
+    }
+    let history = notifier.history();
+    let result = match history.lock() {
+        Ok(history) => history.persist(app),
+        Err(e) => Err(e.to_string()),
+    };
+    if let Err(e) = result {
+        eprintln!("Failed to persist notification history: {}", e);
+    }
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     let keychain = KeychainManager::new();
@@ -29,6 +67,9 @@ pub fn run() {
         rate_limit: None,
         error: None,
         last_updated: 0,
+        backoff: None,
+        stale: false,
+        offline: false,
     }));
     
     let openai_status = Arc::new(RwLock::new(ProviderStatus {
@@ -40,6 +81,9 @@ pub fn run() {
         rate_limit: None,
         error: None,
         last_updated: 0,
+        backoff: None,
+        stale: false,
+        offline: false,
     }));
     
     let gemini_status = Arc::new(RwLock::new(ProviderStatus {
@@ -51,9 +95,15 @@ pub fn run() {
         rate_limit: None,
         error: None,
         last_updated: 0,
+        backoff: None,
+        stale: false,
+        offline: false,
     }));
     
-    let notification_service = Arc::new(RwLock::new(NotificationService::new()));
+    let http = HttpClient::default();
+    let shutdown = CancellationToken::new();
+    let notification_service = Arc::new(RwLock::new(NotificationService::new(http.clone(), shutdown.clone())));
+    let polling = PollingHandle::new(shutdown.clone());
     
     let app_state = AppState {
         keychain,
@@ -61,6 +111,12 @@ pub fn run() {
         openai_status: Arc::clone(&openai_status),
         gemini_status: Arc::clone(&gemini_status),
         notification_service: Arc::clone(&notification_service),
+        oauth_flow: Arc::new(tokio::sync::Mutex::new(None)),
+        pending_oauth: Arc::new(tokio::sync::Mutex::new(None)),
+        polling: polling.clone(),
+        http: http.clone(),
+        shutdown: shutdown.clone(),
+        polling_task: Arc::new(tokio::sync::Mutex::new(None)),
     };
     
     tauri::Builder::default()
@@ -72,22 +128,69 @@ pub fn run() {
         .manage(app_state)
         .invoke_handler(tauri::generate_handler![
             commands::get_provider_status,
+            commands::refresh_provider,
+            commands::refresh_all,
             commands::save_api_key,
             commands::remove_api_key,
             commands::save_threshold,
+            commands::list_thresholds,
+            commands::delete_threshold,
+            commands::save_alert_rule,
+            commands::list_alert_rules,
+            commands::delete_alert_rule,
+            commands::get_reset_notifications,
+            commands::set_reset_notifications,
+            commands::list_sinks,
+            commands::save_sink,
+            commands::delete_sink,
+            commands::test_sink,
+            commands::get_quiet_hours,
+            commands::set_quiet_hours,
+            commands::list_notification_history,
+            commands::acknowledge_notification,
+            commands::acknowledge_all_notifications,
+            commands::clear_notification_history,
             commands::start_oauth_flow,
+            commands::cancel_oauth_flow,
+            commands::begin_oauth_paste_flow,
+            commands::submit_oauth_code,
             commands::check_oauth_credentials,
+            commands::get_token_refresh_policy,
+            commands::set_token_refresh_policy,
+            commands::get_oauth_token_storage,
+            commands::set_oauth_token_storage,
+            commands::get_power_state,
+            commands::get_http_settings,
+            commands::set_http_settings,
+            commands::get_poll_settings,
+            commands::set_poll_settings,
             commands::update_tray_icon,
             commands::update_tray_menu,
             commands::send_test_notification,
         ])
         .setup(move |app| {
+            if let Some(policy) = settings::load::<RefreshPolicy>(app.handle(), settings::TOKEN_REFRESH_POLICY) {
+                match policy.validate() {
+                    Ok(()) => ClaudeOAuthClient::set_refresh_policy(policy),
+                    Err(e) => eprintln!("Ignoring saved token refresh policy: {}", e),
+                }
+            }
+            ClaudeOAuthClient::set_token_storage(
+                settings::load(app.handle(), settings::OAUTH_TOKEN_STORAGE).unwrap_or_default(),
+            );
+            if let Some(http_settings) = settings::load(app.handle(), settings::HTTP_SETTINGS) {
+                if let Err(e) = http.reconfigure(http_settings) {
+                    eprintln!("Ignoring saved HTTP settings: {}", e);
+                }
+            }
+            
             let icon_data = tray::generate_disconnected_icon();
             let icon = Image::new_owned(icon_data, 22, 22);
             
+            let refresh_item = MenuItem::with_id(app, "refresh", "Refresh Now", true, None::<&str>)?;
             let show_item = MenuItem::with_id(app, "show", "Show Window", true, None::<&str>)?;
             let quit_item = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
-            let menu = Menu::with_items(app, &[&show_item, &quit_item])?;
+            let menu = Menu::with_items(app, &[&refresh_item, &show_item, &quit_item])?;
             
             let _tray = TrayIconBuilder::with_id("main")
                 .icon(icon)
@@ -95,6 +198,14 @@ pub fn run() {
                 .show_menu_on_left_click(false)
                 .on_menu_event(|app, event| {
                     match event.id.as_ref() {
+                        "refresh" => {
+                            let polling = app.state::<AppState>().polling.clone();
+                            tauri::async_runtime::spawn(async move {
+                                if let Err(e) = polling.refresh_all().await {
+                                    eprintln!("Refresh failed: {}", e);
+                                }
+                            });
+                        }
                         "show" => {
                             if let Some(window) = app.get_webview_window("main") {
                                 let _ = window.show();
@@ -117,17 +228,36 @@ pub fn run() {
                 })
                 .build(app)?;
             
+            // Nothing else holds the lock yet, so this cannot fail; it has to happen
+            // before polling starts or the first check would run with no thresholds.
+            if let Ok(mut notifier) = notification_service.try_write() {
+                notifier.load_persisted(app.handle());
+            }
+            if let Some(poll_settings) = settings::load::<PollSettings>(app.handle(), settings::POLL_SETTINGS) {
+                match poll_settings.validate() {
+                    Ok(()) => {
+                        if let Ok(mut current) = polling.settings.try_write() {
+                            *current = poll_settings;
+                        }
+                    }
+                    Err(e) => eprintln!("Ignoring saved poll settings, using defaults: {}", e),
+                }
+            }
+            
             let polling_service = PollingService::new(
                 app.handle().clone(),
                 Arc::clone(&anthropic_status),
                 Arc::clone(&openai_status),
+                Arc::clone(&gemini_status),
                 Arc::clone(&notification_service),
-                60,
+                polling.clone(),
+                http.clone(),
             );
             
-            tauri::async_runtime::spawn(async move {
-                polling_service.start().await;
-            });
+            let polling_task = tauri::async_runtime::spawn(polling_service.start());
+            if let Ok(mut slot) = app.state::<AppState>().polling_task.try_lock() {
+                *slot = Some(polling_task);
+            }
             
             Ok(())
         })
@@ -137,6 +267,23 @@ pub fn run() {
                 let _ = window.hide();
             }
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| match event {
+            // Covers the tray item, the app menu (Cmd+Q) and OS logout alike.
+            tauri::RunEvent::ExitRequested { api, code, .. } => {
+                if app.state::<AppState>().shutdown.is_cancelled() {
+                    return;
+                }
+                api.prevent_exit();
+                let app = app.clone();
+                tauri::async_runtime::spawn(async move {
+                    graceful_shutdown(&app).await;
+                    app.exit(code.unwrap_or(0));
+                });
+            }
+            // Exits that skipped `ExitRequested` still get their state flushed.
+            tauri::RunEvent::Exit => tauri::async_runtime::block_on(graceful_shutdown(app)),
+            _ => {}
+        });
 }
@@ -0,0 +1,97 @@
+use std::collections::VecDeque;
+
+/// Fewer samples than this give too noisy a slope to act on.
+const MIN_SAMPLES: usize = 3;
+const MIN_SPAN_SECS: u64 = 5 * 60;
+
+/// Recent utilization readings for one usage window.
+pub struct UsageHistory {
+    lookback_secs: u64,
+    samples: VecDeque<(u64, f64)>,
+}
+
+impl UsageHistory {
+    pub fn new(lookback_secs: u64) -> Self {
+        Self {
+            lookback_secs,
+            samples: VecDeque::new(),
+        }
+    }
+
+    pub fn record(&mut self, at_secs: u64, utilization: f64) {
+        // A drop means the window reset; older samples describe a different window.
+        // If the clock stepped backwards the old timestamps can't be compared either.
+        if self.samples.back().is_some_and(|&(t, last)| utilization < last || at_secs < t) {
+            self.samples.clear();
+        }
+        self.samples.push_back((at_secs, utilization));
+
+        let cutoff = at_secs.saturating_sub(self.lookback_secs);
+        while self.samples.front().is_some_and(|&(t, _)| t < cutoff) {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Minutes until utilization reaches 100% at the recent rate, or `None` when
+    /// usage is flat or falling, there is not enough data, or the window resets first.
+    pub fn minutes_to_exhaustion(&self, now_secs: u64, resets_at_secs: Option<u64>) -> Option<f64> {
+        let slope = self.slope_per_sec()?;
+        if slope <= 0.0 {
+            return None;
+        }
+
+        let &(_, current) = self.samples.back()?;
+        let secs_left = (100.0 - current).max(0.0) / slope;
+
+        if let Some(resets_at) = resets_at_secs {
+            if resets_at.saturating_sub(now_secs) as f64 <= secs_left {
+                return None;
+            }
+        }
+
+        Some(secs_left / 60.0)
+    }
+
+    /// Least-squares slope of utilization over time, in percentage points per second.
+    fn slope_per_sec(&self) -> Option<f64> {
+        let (first, _) = *self.samples.front()?;
+        let (last, _) = *self.samples.back()?;
+        if self.samples.len() < MIN_SAMPLES || last - first < MIN_SPAN_SECS {
+            return None;
+        }
+
+        let n = self.samples.len() as f64;
+        let xs = self.samples.iter().map(|&(t, _)| (t - first) as f64);
+        let mean_x = xs.clone().sum::<f64>() / n;
+        let mean_y = self.samples.iter().map(|&(_, u)| u).sum::<f64>() / n;
+
+        let (mut cov, mut var) = (0.0, 0.0);
+        for (x, &(_, y)) in xs.zip(self.samples.iter()) {
+            cov += (x - mean_x) * (y - mean_y);
+            var += (x - mean_x) * (x - mean_x);
+        }
+
+        (var > 0.0).then(|| cov / var)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clock_stepping_back_restarts_the_history() {
+        let mut history = UsageHistory::new(60 * 60);
+        history.record(10_000, 10.0);
+        history.record(10_300, 20.0);
+        history.record(10_600, 30.0);
+        assert!(history.minutes_to_exhaustion(10_600, None).is_some());
+
+        history.record(9_000, 35.0);
+        assert_eq!(history.minutes_to_exhaustion(9_000, None), None);
+
+        history.record(9_300, 40.0);
+        history.record(9_600, 45.0);
+        assert!(history.minutes_to_exhaustion(9_600, None).is_some());
+    }
+}
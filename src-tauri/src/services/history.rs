@@ -0,0 +1,150 @@
+use super::notification::{now_secs, AlertSeverity};
+use super::sinks::AlertMessage;
+use crate::storage::settings;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+const MAX_RECORDS: usize = 500;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    Delivered,
+    Failed,
+    /// Held back by quiet hours and included in the summary afterwards.
+    Queued,
+    /// Not sent at all, e.g. because a more severe rule fired for the same metric.
+    Suppressed,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DeliveryResult {
+    /// `"desktop"` or the sink name.
+    pub channel: String,
+    pub status: DeliveryStatus,
+    pub detail: Option<String>,
+}
+
+impl DeliveryResult {
+    pub fn new(channel: impl Into<String>, status: DeliveryStatus, detail: Option<String>) -> Self {
+        Self {
+            channel: channel.into(),
+            status,
+            detail,
+        }
+    }
+}
+
+/// What triggered an alert, for auditing.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct AlertContext {
+    pub rule_id: Option<String>,
+    pub rule_name: Option<String>,
+    pub metric: Option<String>,
+    pub value: Option<f64>,
+    pub threshold: Option<f64>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct NotificationRecord {
+    pub id: u64,
+    pub timestamp: u64,
+    pub provider: String,
+    pub severity: AlertSeverity,
+    pub title: String,
+    pub body: String,
+    #[serde(flatten)]
+    pub context: AlertContext,
+    pub deliveries: Vec<DeliveryResult>,
+    pub acknowledged: bool,
+}
+
+#[derive(Default)]
+pub struct NotificationHistory {
+    records: VecDeque<NotificationRecord>,
+    next_id: u64,
+}
+
+impl NotificationHistory {
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        let records: VecDeque<NotificationRecord> =
+            settings::load(app, settings::NOTIFICATION_HISTORY).unwrap_or_default();
+        let next_id = records.iter().map(|r| r.id + 1).max().unwrap_or(1);
+        Self { records, next_id }
+    }
+
+    pub fn persist(&self, app: &tauri::AppHandle) -> Result<(), String> {
+        settings::save(app, settings::NOTIFICATION_HISTORY, &self.records)
+    }
+
+    pub fn push(&mut self, alert: &AlertMessage, context: AlertContext, deliveries: Vec<DeliveryResult>) -> u64 {
+        let id = self.next_id.max(1);
+        self.next_id = id + 1;
+
+        self.records.push_back(NotificationRecord {
+            id,
+            timestamp: now_secs(),
+            provider: alert.provider.clone(),
+            severity: alert.severity,
+            title: alert.title.clone(),
+            body: alert.body.clone(),
+            context,
+            deliveries,
+            acknowledged: false,
+        });
+        while self.records.len() > MAX_RECORDS {
+            self.records.pop_front();
+        }
+        id
+    }
+
+    pub fn record_delivery(&mut self, id: u64, result: DeliveryResult) {
+        if let Some(record) = self.records.iter_mut().find(|r| r.id == id) {
+            record.deliveries.push(result);
+        }
+    }
+
+    /// Settles desktop deliveries held by quiet hours once summary `summary_id` went out.
+    pub fn resolve_queued(&mut self, summary_id: u64, status: DeliveryStatus) {
+        let detail = format!("Included in quiet hours summary #{}", summary_id);
+        for record in self.records.iter_mut().filter(|r| r.id != summary_id) {
+            for delivery in &mut record.deliveries {
+                if delivery.channel == "desktop" && delivery.status == DeliveryStatus::Queued {
+                    delivery.status = status;
+                    delivery.detail = Some(detail.clone());
+                }
+            }
+        }
+    }
+
+    /// Newest first.
+    pub fn list(&self, limit: Option<usize>, unacknowledged_only: bool) -> Vec<NotificationRecord> {
+        self.records
+            .iter()
+            .rev()
+            .filter(|r| !unacknowledged_only || !r.acknowledged)
+            .take(limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect()
+    }
+
+    pub fn acknowledge(&mut self, id: u64) -> bool {
+        match self.records.iter_mut().find(|r| r.id == id) {
+            Some(record) => {
+                record.acknowledged = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn acknowledge_all(&mut self) {
+        for record in &mut self.records {
+            record.acknowledged = true;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.records.clear();
+    }
+}
@@ -1,6 +1,17 @@
 pub mod polling;
 pub mod response_types;
+pub mod forecast;
+pub mod history;
 pub mod notification;
+pub mod power;
+pub mod quiet_hours;
+pub mod rules;
+pub mod sinks;
 
-pub use response_types::{ProviderStatus, UsageSnapshot, ClaudeUsageSnapshot, CodexUsageSnapshot};
-pub use notification::{NotificationService, NotificationThreshold};
+pub use response_types::{ProviderStatus, BackoffState, UsageSnapshot, ClaudeUsageSnapshot, CodexUsageSnapshot};
+pub use notification::{
+    AlertSeverity, NotificationService, NotificationThreshold, ResetNotificationSettings, UsageWindow,
+};
+pub use sinks::{AlertMessage, NotificationSink, SinkTarget};
+pub use quiet_hours::{QuietHours, TimeRange};
+pub use rules::AlertRule;
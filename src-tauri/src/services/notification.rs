@@ -1,7 +1,23 @@
+use crate::api::http::HttpClient;
 use crate::services::ProviderStatus;
+use crate::services::forecast::UsageHistory;
+use crate::services::history::{AlertContext, DeliveryResult, DeliveryStatus, NotificationHistory};
+use crate::services::quiet_hours::QuietHours;
+use crate::services::rules::{AlertRule, RuleContext};
+use crate::services::sinks::{AlertMessage, NotificationSink};
+use crate::storage::settings;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
 use tauri_plugin_notification::NotificationExt;
+use tokio_util::sync::CancellationToken;
+
+pub const DEFAULT_REARM_MARGIN_PCT: f64 = 5.0;
+
+/// Alerts held back during quiet hours beyond this drop the oldest; the summary still counts them.
+const MAX_QUIET_QUEUE: usize = 100;
+const PERIOD_FORECAST_LOOKBACK_SECS: u64 = 30 * 60;
+const WEEKLY_FORECAST_LOOKBACK_SECS: u64 = 6 * 60 * 60;
 
 pub fn show_app_notification(
     app: &tauri::AppHandle,
@@ -20,172 +36,837 @@ pub fn show_app_notification(
         .map_err(|e| e.to_string())
 }
 
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertSeverity {
+    Info,
+    #[default]
+    Warning,
+    Urgent,
+    Critical,
+}
+
+impl AlertSeverity {
+    fn title_prefix(self) -> &'static str {
+        match self {
+            Self::Info => "Usage Notice",
+            Self::Warning => "Usage Alert",
+            Self::Urgent => "Urgent",
+            Self::Critical => "Critical",
+        }
+    }
+}
+
+/// A named alert rule. A provider may have any number of these, e.g. warn at 50%,
+/// urgent at 80% and critical at 95%.
+///
+/// Rules saved without an `id` get a generated one, so they never replace each other.
 #[derive(Clone, Serialize, Deserialize)]
 pub struct NotificationThreshold {
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub name: Option<String>,
     pub provider: String,
     pub token_limit: Option<u64>,
     pub cost_limit: Option<f64>,
     pub rate_limit_percentage: Option<u8>,
     pub period_utilization_threshold: Option<f64>,
     pub weekly_utilization_threshold: Option<f64>,
+    #[serde(default)]
+    pub severity: AlertSeverity,
+    /// Body text with `{provider}`, `{rule}`, `{metric}`, `{value}`, `{threshold}`
+    /// and `{severity}` placeholders. The built-in message is used when unset.
+    #[serde(default)]
+    pub message_template: Option<String>,
+    /// How far, as a percentage of the limit, a value has to move back before the
+    /// alert re-arms. Defaults to [`DEFAULT_REARM_MARGIN_PCT`].
+    #[serde(default)]
+    pub rearm_margin_pct: Option<f64>,
+    /// Repeat a still-firing alert after this many minutes. Never repeats when unset.
+    #[serde(default)]
+    pub repeat_cooldown_mins: Option<u64>,
+    /// Warn when a usage window is projected to run out within this many minutes
+    /// at the recent rate, unless it resets first.
+    #[serde(default)]
+    pub exhaustion_warning_mins: Option<u64>,
     pub enabled: bool,
 }
 
+impl NotificationThreshold {
+    fn display_name(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.id)
+    }
+
+    fn render_body(&self, provider: &str, metric: &str, value: &str, limit: &str, default_body: String) -> String {
+        match &self.message_template {
+            Some(template) => template
+                .replace("{provider}", provider)
+                .replace("{rule}", self.display_name())
+                .replace("{metric}", metric)
+                .replace("{value}", value)
+                .replace("{threshold}", limit)
+                .replace("{severity}", &format!("{:?}", self.severity).to_lowercase()),
+            None => default_body,
+        }
+    }
+}
+
+/// Per-window opt-in for "you're back to 100%" notifications.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ResetNotificationSettings {
+    pub period: bool,
+    pub weekly: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UsageWindow {
+    FiveHour,
+    Weekly,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Direction {
+    /// Fires when the value reaches the limit (utilization, cost, tokens).
+    Above,
+    /// Fires when the value drops under the limit (remaining rate limit).
+    Below,
+}
+
+/// One metric of one rule, measured against its limit.
+struct Reading {
+    metric: &'static str,
+    value: f64,
+    limit: f64,
+    direction: Direction,
+    title: String,
+    body: String,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+struct AlertState {
+    firing: bool,
+    last_fired_at: u64,
+}
+
 pub struct NotificationService {
     thresholds: Vec<NotificationThreshold>,
-    notified_keys: HashSet<String>,
+    rules: Vec<AlertRule>,
+    /// When each provider that was connected, or is reporting errors, went down.
+    down_since: HashMap<String, u64>,
+    seen_connected: HashSet<String>,
+    alert_states: HashMap<String, AlertState>,
+    reset_settings: ResetNotificationSettings,
+    sinks: Vec<NotificationSink>,
+    quiet_hours: QuietHours,
+    quiet_queue: Vec<AlertMessage>,
+    /// Queued alerts dropped for exceeding [`MAX_QUIET_QUEUE`].
+    quiet_dropped: usize,
+    usage_history: HashMap<String, UsageHistory>,
+    /// Shared with sink delivery tasks, which record their results as they finish.
+    history: Arc<Mutex<NotificationHistory>>,
+    http: HttpClient,
+    /// Sink deliveries still running on quit are abandoned once this is cancelled.
+    shutdown: CancellationToken,
 }
 
 impl NotificationService {
-    pub fn new() -> Self {
+    pub fn new(http: HttpClient, shutdown: CancellationToken) -> Self {
         Self {
             thresholds: Vec::new(),
-            notified_keys: HashSet::new(),
+            rules: Vec::new(),
+            down_since: HashMap::new(),
+            seen_connected: HashSet::new(),
+            alert_states: HashMap::new(),
+            reset_settings: ResetNotificationSettings::default(),
+            sinks: Vec::new(),
+            quiet_hours: QuietHours::default(),
+            quiet_queue: Vec::new(),
+            quiet_dropped: 0,
+            usage_history: HashMap::new(),
+            history: Arc::new(Mutex::new(NotificationHistory::default())),
+            http,
+            shutdown,
+        }
+    }
+
+    /// Restores thresholds and alert state saved by a previous run.
+    pub fn load_persisted(&mut self, app: &tauri::AppHandle) {
+        if let Some(thresholds) = settings::load::<Vec<NotificationThreshold>>(app, settings::NOTIFICATION_THRESHOLDS) {
+            self.thresholds = thresholds.into_iter().map(Self::with_id).collect();
+        }
+        if let Some(rules) = settings::load(app, settings::ALERT_RULES) {
+            self.rules = rules;
+        }
+        if let Some(states) = settings::load(app, settings::ALERT_STATES) {
+            self.alert_states = states;
+        }
+        if let Some(reset_settings) = settings::load(app, settings::RESET_NOTIFICATIONS) {
+            self.reset_settings = reset_settings;
+        }
+        if let Some(sinks) = settings::load(app, settings::NOTIFICATION_SINKS) {
+            self.sinks = sinks;
+        }
+        if let Some(quiet_hours) = settings::load(app, settings::QUIET_HOURS) {
+            self.quiet_hours = quiet_hours;
+        }
+        if let Some(mut queue) = settings::load::<Vec<AlertMessage>>(app, settings::QUIET_QUEUE) {
+            self.quiet_dropped = queue.len().saturating_sub(MAX_QUIET_QUEUE);
+            queue.drain(..self.quiet_dropped);
+            self.quiet_queue = queue;
+        }
+        *self.history.lock().unwrap() = NotificationHistory::load(app);
+    }
+
+    pub fn persist(&self, app: &tauri::AppHandle) -> Result<(), String> {
+        settings::save(app, settings::NOTIFICATION_THRESHOLDS, &self.thresholds)?;
+        settings::save(app, settings::ALERT_RULES, &self.rules)?;
+        settings::save(app, settings::ALERT_STATES, &self.alert_states)?;
+        settings::save(app, settings::RESET_NOTIFICATIONS, &self.reset_settings)?;
+        settings::save(app, settings::NOTIFICATION_SINKS, &self.sinks)?;
+        settings::save(app, settings::QUIET_HOURS, &self.quiet_hours)?;
+        settings::save(app, settings::QUIET_QUEUE, &self.quiet_queue)
+    }
+
+    pub fn history(&self) -> Arc<Mutex<NotificationHistory>> {
+        self.history.clone()
+    }
+
+    pub fn quiet_hours(&self) -> &QuietHours {
+        &self.quiet_hours
+    }
+
+    pub fn set_quiet_hours(&mut self, quiet_hours: QuietHours) -> Result<(), String> {
+        quiet_hours.validate()?;
+        self.quiet_hours = quiet_hours;
+        Ok(())
+    }
+
+    /// Once quiet hours are over, sends one summary of everything held back.
+    pub fn flush_quiet_queue(&mut self, app: &tauri::AppHandle) {
+        if self.quiet_queue.is_empty() || self.quiet_hours.is_active(chrono::Utc::now()) {
+            return;
+        }
+
+        let queued = std::mem::take(&mut self.quiet_queue);
+        let total = queued.len() + std::mem::take(&mut self.quiet_dropped);
+        let mut lines: Vec<String> = queued.iter().take(5).map(|a| format!("• {}", a.title)).collect();
+        if total > 5 {
+            lines.push(format!("…and {} more", total - 5));
+        }
+        let title = format!(
+            "{} alert{} during quiet hours",
+            total,
+            if total == 1 { "" } else { "s" }
+        );
+        let provider = match queued.first() {
+            Some(first) if queued.iter().all(|a| a.provider == first.provider) => first.provider.clone(),
+            _ => "all".to_string(),
+        };
+        let severity = queued.iter().map(|a| a.severity).max().unwrap_or_default();
+        let summary = AlertMessage::new(title, lines.join("\n"), provider, severity);
+
+        let desktop = match show_app_notification(app, &summary.title, &summary.body) {
+            Ok(()) => DeliveryResult::new("desktop", DeliveryStatus::Delivered, None),
+            Err(err) => {
+                eprintln!("Failed to send quiet hours summary: {}", err);
+                DeliveryResult::new("desktop", DeliveryStatus::Failed, Some(err))
+            }
+        };
+        // The queued alerts' history entries now point at the summary that carried them.
+        let status = desktop.status;
+        {
+            let mut history = self.history.lock().unwrap();
+            let summary_id = history.push(&summary, AlertContext::default(), vec![desktop]);
+            history.resolve_queued(summary_id, status);
+            if let Err(e) = history.persist(app) {
+                eprintln!("Failed to persist notification history: {}", e);
+            }
+        }
+
+        if let Err(e) = settings::save(app, settings::QUIET_QUEUE, &self.quiet_queue) {
+            eprintln!("Failed to persist quiet hours queue: {}", e);
+        }
+    }
+
+    pub fn sinks(&self) -> &[NotificationSink] {
+        &self.sinks
+    }
+
+    pub fn sink(&self, id: &str) -> Option<&NotificationSink> {
+        self.sinks.iter().find(|s| s.id == id)
+    }
+
+    pub fn save_sink(&mut self, mut sink: NotificationSink) -> Result<String, String> {
+        sink.validate()?;
+        if sink.id.is_empty() {
+            sink.id = format!("sink-{:08x}", rand::random::<u32>());
         }
+        let id = sink.id.clone();
+        self.sinks.retain(|s| s.id != id);
+        self.sinks.push(sink);
+        Ok(id)
+    }
+
+    pub fn remove_sink(&mut self, id: &str) -> bool {
+        let before = self.sinks.len();
+        self.sinks.retain(|s| s.id != id);
+        self.sinks.len() != before
+    }
+
+    pub fn reset_settings(&self) -> &ResetNotificationSettings {
+        &self.reset_settings
+    }
+
+    pub fn set_reset_settings(&mut self, reset_settings: ResetNotificationSettings) {
+        self.reset_settings = reset_settings;
     }
 
-    pub fn add_threshold(&mut self, threshold: NotificationThreshold) {
-        self.thresholds.retain(|t| t.provider != threshold.provider);
+    /// Tells the user a usage window has started over, if they opted in for it.
+    pub fn notify_window_reset(&mut self, window: UsageWindow, utilization: f64, app: &tauri::AppHandle) {
+        let (enabled, metric, title, label) = match window {
+            UsageWindow::FiveHour => (self.reset_settings.period, "period_reset", "5-Hour Window Reset", "5-hour window reset"),
+            UsageWindow::Weekly => (self.reset_settings.weekly, "weekly_reset", "Weekly Window Reset", "Weekly window reset"),
+        };
+        if !enabled {
+            return;
+        }
+
+        let remaining = (100.0 - utilization).clamp(0.0, 100.0);
+        self.send_notification(
+            app,
+            AlertMessage::new(
+                title,
+                format!("{} — you're back to {:.0}%", label, remaining),
+                "anthropic",
+                AlertSeverity::Info,
+            ),
+            AlertContext {
+                metric: Some(metric.to_string()),
+                value: Some(utilization),
+                ..AlertContext::default()
+            },
+        );
+    }
+
+    /// Warns that a provider's numbers haven't been refreshed for a while.
+    pub fn notify_stale(&mut self, provider: &str, minutes: u64, app: &tauri::AppHandle) {
+        self.send_notification(
+            app,
+            AlertMessage::new(
+                "Usage Data Is Stale",
+                format!(
+                    "{} usage hasn't updated in {} minutes; the numbers shown may be out of date",
+                    provider, minutes
+                ),
+                provider,
+                AlertSeverity::Warning,
+            ),
+            AlertContext {
+                metric: Some("stale".to_string()),
+                value: Some(minutes as f64),
+                ..AlertContext::default()
+            },
+        );
+    }
+
+    pub fn thresholds(&self) -> &[NotificationThreshold] {
+        &self.thresholds
+    }
+
+    pub fn add_threshold(&mut self, threshold: NotificationThreshold) -> String {
+        let threshold = Self::with_id(threshold);
+        let id = threshold.id.clone();
+        self.thresholds.retain(|t| t.id != id);
+        self.clear_keys_for(&id);
         self.thresholds.push(threshold);
+        id
+    }
+
+    pub fn remove_threshold(&mut self, id: &str) -> bool {
+        let before = self.thresholds.len();
+        self.thresholds.retain(|t| t.id != id);
+        self.clear_keys_for(id);
+        self.thresholds.len() != before
+    }
+
+    pub fn rules(&self) -> &[AlertRule] {
+        &self.rules
+    }
+
+    pub fn save_rule(&mut self, mut rule: AlertRule) -> Result<String, String> {
+        rule.validate()?;
+        if rule.id.is_empty() {
+            rule.id = format!("rule-{:08x}", rand::random::<u32>());
+        }
+        let id = rule.id.clone();
+        self.rules.retain(|r| r.id != id);
+        self.alert_states.remove(&Self::rule_key(&id));
+        self.rules.push(rule);
+        Ok(id)
+    }
+
+    pub fn remove_rule(&mut self, id: &str) -> bool {
+        let before = self.rules.len();
+        self.rules.retain(|r| r.id != id);
+        self.alert_states.remove(&Self::rule_key(id));
+        self.rules.len() != before
+    }
+
+    fn rule_key(id: &str) -> String {
+        format!("rule:{}", id)
+    }
+
+    /// Evaluates every alert rule against the latest status of all providers.
+    pub fn evaluate_rules(&mut self, statuses: &[ProviderStatus], app: &tauri::AppHandle) {
+        let now = now_secs();
+        for status in statuses {
+            // Being offline says nothing about the provider, so leave its downtime as it was.
+            if status.offline {
+                continue;
+            }
+            if status.connected {
+                self.seen_connected.insert(status.provider.clone());
+                self.down_since.remove(&status.provider);
+            } else if status.error.is_some() || self.seen_connected.contains(&status.provider) {
+                self.down_since.entry(status.provider.clone()).or_insert(now);
+            }
+        }
+
+        let states_before = self.alert_states.clone();
+        let rules: Vec<AlertRule> = self.rules.iter().filter(|r| r.enabled).cloned().collect();
+        for rule in rules {
+            let ctx = RuleContext {
+                statuses,
+                down_since: &self.down_since,
+                now,
+            };
+            let holds = rule.condition.evaluate(&ctx);
+            let margin_pct = rule.rearm_margin_pct.unwrap_or(DEFAULT_REARM_MARGIN_PCT);
+            let rearmed = !rule.condition.evaluate_with_margin(&ctx, margin_pct);
+
+            if self.step(&Self::rule_key(&rule.id), holds, rearmed, rule.repeat_cooldown_mins, now) {
+                let alert = AlertMessage::new(
+                    format!("{}: {}", rule.severity.title_prefix(), rule.name),
+                    rule.body(),
+                    "any",
+                    rule.severity,
+                );
+                let context = AlertContext {
+                    rule_id: Some(rule.id.clone()),
+                    rule_name: Some(rule.name.clone()),
+                    ..AlertContext::default()
+                };
+                self.send_notification(app, alert, context);
+            }
+        }
+
+        if self.alert_states != states_before {
+            if let Err(e) = settings::save(app, settings::ALERT_STATES, &self.alert_states) {
+                eprintln!("Failed to persist notification state: {}", e);
+            }
+        }
+    }
+
+    /// Time spent asleep counts neither towards downtime nor towards repeat
+    /// cooldowns, so rules that held before a suspend don't all fire again on wake.
+    pub fn discount_sleep(&mut self, slept_secs: u64, app: &tauri::AppHandle) {
+        for since in self.down_since.values_mut() {
+            *since += slept_secs;
+        }
+        if self.alert_states.is_empty() {
+            return;
+        }
+        for state in self.alert_states.values_mut() {
+            state.last_fired_at += slept_secs;
+        }
+        if let Err(e) = settings::save(app, settings::ALERT_STATES, &self.alert_states) {
+            eprintln!("Failed to persist notification state: {}", e);
+        }
     }
 
     pub fn reset_notifications(&mut self) {
-        self.notified_keys.clear();
+        self.alert_states.clear();
+    }
+
+    fn with_id(mut threshold: NotificationThreshold) -> NotificationThreshold {
+        if threshold.id.is_empty() {
+            threshold.id = format!("threshold-{:08x}", rand::random::<u32>());
+        }
+        threshold
+    }
+
+    fn clear_keys_for(&mut self, id: &str) {
+        let marker = format!(":{}:", id);
+        self.alert_states.retain(|k, _| !k.contains(&marker));
     }
 
     pub fn check_and_notify(&mut self, status: &ProviderStatus, app: &tauri::AppHandle) {
-        let threshold = self
-            .thresholds
-            .iter()
-            .find(|t| t.provider == status.provider)
-            .cloned();
+        let states_before = self.alert_states.clone();
+        self.record_usage(status, now_secs());
+        self.evaluate_thresholds(status, app);
 
-        if let Some(threshold) = threshold {
-            if !threshold.enabled {
-                return;
+        if self.alert_states != states_before {
+            if let Err(e) = self.persist(app) {
+                eprintln!("Failed to persist notification state: {}", e);
             }
-
-            self.check_claude_usage(status, &threshold, app);
-            self.check_legacy_usage(status, &threshold, app);
-            self.check_rate_limit(status, &threshold, app);
         }
     }
 
-    fn check_claude_usage(
-        &mut self,
-        status: &ProviderStatus,
-        threshold: &NotificationThreshold,
-        app: &tauri::AppHandle,
-    ) {
-        if let Some(claude_usage) = &status.claude_usage {
-            if let Some(period_threshold) = threshold.period_utilization_threshold {
-                if claude_usage.period_utilization >= period_threshold {
-                    let key = format!("{}:period:{}", status.provider, period_threshold);
-                    if !self.notified_keys.contains(&key) {
-                        self.send_notification(
-                            app,
-                            "Usage Alert: 5-Hour Limit",
-                            &format!(
-                                "5-hour utilization at {:.0}% (threshold: {:.0}%)",
-                                claude_usage.period_utilization, period_threshold
-                            ),
-                        );
-                        self.notified_keys.insert(key);
-                    }
+    fn evaluate_thresholds(&mut self, status: &ProviderStatus, app: &tauri::AppHandle) {
+        // Most severe first, so that when a jump crosses several tiers at once only
+        // the highest one notifies and the lower ones are marked as already sent.
+        let mut rules: Vec<NotificationThreshold> = self
+            .thresholds
+            .iter()
+            .filter(|t| t.enabled && t.provider == status.provider)
+            .cloned()
+            .collect();
+        rules.sort_by_key(|t| std::cmp::Reverse(t.severity));
+
+        let now = now_secs();
+        let mut sent_metrics = HashSet::new();
+        for threshold in &rules {
+            let mut readings = Self::claude_usage_readings(status, threshold);
+            readings.extend(Self::legacy_usage_readings(status, threshold));
+            readings.extend(Self::rate_limit_readings(status, threshold));
+            readings.extend(self.exhaustion_readings(status, threshold, now));
+
+            for reading in readings {
+                let key = format!("{}:{}:{}:{}", status.provider, threshold.id, reading.metric, reading.limit);
+                if !self.advance(&key, &reading, threshold, now) {
+                    continue;
+                }
+
+                let context = AlertContext {
+                    rule_id: Some(threshold.id.clone()),
+                    rule_name: threshold.name.clone(),
+                    metric: Some(reading.metric.to_string()),
+                    value: Some(reading.value),
+                    threshold: Some(reading.limit),
+                };
+                let alert = AlertMessage::new(reading.title, reading.body, &status.provider, threshold.severity);
+                if sent_metrics.insert(reading.metric) {
+                    self.send_notification(app, alert, context);
                 } else {
-                    let key = format!("{}:period:{}", status.provider, period_threshold);
-                    self.notified_keys.remove(&key);
+                    let suppressed = DeliveryResult::new(
+                        "desktop",
+                        DeliveryStatus::Suppressed,
+                        Some("A more severe rule already alerted on this metric".to_string()),
+                    );
+                    self.record(app, &alert, context, vec![suppressed]);
                 }
             }
+        }
+    }
 
-            if let Some(weekly_threshold) = threshold.weekly_utilization_threshold {
-                if let Some(weekly_util) = claude_usage.weekly_utilization {
-                    if weekly_util >= weekly_threshold {
-                        let key = format!("{}:weekly:{}", status.provider, weekly_threshold);
-                        if !self.notified_keys.contains(&key) {
-                            self.send_notification(
-                                app,
-                                "Usage Alert: Weekly Limit",
-                                &format!(
-                                    "Weekly utilization at {:.0}% (threshold: {:.0}%)",
-                                    weekly_util, weekly_threshold
-                                ),
-                            );
-                            self.notified_keys.insert(key);
-                        }
-                    } else {
-                        let key = format!("{}:weekly:{}", status.provider, weekly_threshold);
-                        self.notified_keys.remove(&key);
-                    }
+    /// Steps the alert state for one threshold reading and returns whether to notify now.
+    fn advance(&mut self, key: &str, reading: &Reading, rule: &NotificationThreshold, now: u64) -> bool {
+        let margin = reading.limit.abs() * rule.rearm_margin_pct.unwrap_or(DEFAULT_REARM_MARGIN_PCT) / 100.0;
+        let (breached, rearmed) = match reading.direction {
+            Direction::Above => (reading.value >= reading.limit, reading.value < reading.limit - margin),
+            Direction::Below => (reading.value < reading.limit, reading.value >= reading.limit + margin),
+        };
+        self.step(key, breached, rearmed, rule.repeat_cooldown_mins, now)
+    }
+
+    /// The state machine shared by every alert kind; returns whether to notify now.
+    ///
+    /// An alert fires once when `breached`, stays quiet while the value hovers
+    /// around the limit, and re-arms only once `rearmed`, i.e. after moving back
+    /// past the margin. A repeat cooldown, if set, re-sends a still-firing alert.
+    fn step(&mut self, key: &str, breached: bool, rearmed: bool, repeat_cooldown_mins: Option<u64>, now: u64) -> bool {
+        match self.alert_states.get_mut(key) {
+            Some(state) if state.firing => {
+                if rearmed {
+                    self.alert_states.remove(key);
+                    return false;
                 }
+                let cooldown_elapsed =
+                    repeat_cooldown_mins.is_some_and(|mins| now.saturating_sub(state.last_fired_at) >= mins * 60);
+                if breached && cooldown_elapsed {
+                    state.last_fired_at = now;
+                    return true;
+                }
+                false
+            }
+            _ if breached => {
+                self.alert_states.insert(key.to_string(), AlertState { firing: true, last_fired_at: now });
+                true
             }
+            _ => false,
         }
     }
 
-    fn check_legacy_usage(
-        &self,
-        status: &ProviderStatus,
-        threshold: &NotificationThreshold,
-        app: &tauri::AppHandle,
-    ) {
-        if let Some(usage) = &status.usage {
-            if let Some(token_limit) = threshold.token_limit {
-                if usage.total_tokens >= token_limit {
-                    self.send_notification(
-                        app,
-                        &format!("{} Token Limit Reached", status.provider),
-                        &format!(
-                            "You've used {} tokens (limit: {})",
-                            usage.total_tokens, token_limit
+    fn claude_usage_readings(status: &ProviderStatus, threshold: &NotificationThreshold) -> Vec<Reading> {
+        let mut readings = Vec::new();
+        let Some(claude_usage) = &status.claude_usage else {
+            return readings;
+        };
+
+        if let Some(period_threshold) = threshold.period_utilization_threshold {
+            readings.push(Reading {
+                metric: "period",
+                value: claude_usage.period_utilization,
+                limit: period_threshold,
+                direction: Direction::Above,
+                title: format!("{}: 5-Hour Limit", threshold.severity.title_prefix()),
+                body: threshold.render_body(
+                    &status.provider,
+                    "5-hour utilization",
+                    &format!("{:.0}%", claude_usage.period_utilization),
+                    &format!("{:.0}%", period_threshold),
+                    format!(
+                        "5-hour utilization at {:.0}% (threshold: {:.0}%)",
+                        claude_usage.period_utilization, period_threshold
+                    ),
+                ),
+            });
+        }
+
+        if let (Some(weekly_threshold), Some(weekly_util)) =
+            (threshold.weekly_utilization_threshold, claude_usage.weekly_utilization)
+        {
+            readings.push(Reading {
+                metric: "weekly",
+                value: weekly_util,
+                limit: weekly_threshold,
+                direction: Direction::Above,
+                title: format!("{}: Weekly Limit", threshold.severity.title_prefix()),
+                body: threshold.render_body(
+                    &status.provider,
+                    "weekly utilization",
+                    &format!("{:.0}%", weekly_util),
+                    &format!("{:.0}%", weekly_threshold),
+                    format!(
+                        "Weekly utilization at {:.0}% (threshold: {:.0}%)",
+                        weekly_util, weekly_threshold
+                    ),
+                ),
+            });
+        }
+
+        readings
+    }
+
+    fn record_usage(&mut self, status: &ProviderStatus, now: u64) {
+        let Some(usage) = &status.claude_usage else {
+            return;
+        };
+
+        self.usage_history
+            .entry(format!("{}:period", status.provider))
+            .or_insert_with(|| UsageHistory::new(PERIOD_FORECAST_LOOKBACK_SECS))
+            .record(now, usage.period_utilization);
+
+        if let Some(weekly) = usage.weekly_utilization {
+            self.usage_history
+                .entry(format!("{}:weekly", status.provider))
+                .or_insert_with(|| UsageHistory::new(WEEKLY_FORECAST_LOOKBACK_SECS))
+                .record(now, weekly);
+        }
+    }
+
+    /// Projected minutes until each window runs out. Windows with no projection
+    /// report infinity so that a firing alert re-arms once the rate settles.
+    fn exhaustion_readings(&self, status: &ProviderStatus, threshold: &NotificationThreshold, now: u64) -> Vec<Reading> {
+        let (Some(warning_mins), Some(usage)) = (threshold.exhaustion_warning_mins, &status.claude_usage) else {
+            return Vec::new();
+        };
+
+        let windows = [
+            ("period_exhaustion", "period", "5-hour", usage.period_resets_at.as_deref()),
+            ("weekly_exhaustion", "weekly", "weekly", usage.weekly_resets_at.as_deref()),
+        ];
+
+        windows
+            .into_iter()
+            .filter_map(|(metric, window, label, resets_at)| {
+                let history = self.usage_history.get(&format!("{}:{}", status.provider, window))?;
+                let resets_at_secs = resets_at
+                    .and_then(|s| chrono::DateTime::parse_from_rfc3339(s).ok())
+                    .map(|at| at.timestamp().max(0) as u64);
+                let minutes = history
+                    .minutes_to_exhaustion(now, resets_at_secs)
+                    .unwrap_or(f64::INFINITY);
+
+                Some(Reading {
+                    metric,
+                    value: minutes,
+                    limit: warning_mins as f64,
+                    direction: Direction::Below,
+                    title: format!("{}: Running Out Soon", threshold.severity.title_prefix()),
+                    body: threshold.render_body(
+                        &status.provider,
+                        &format!("{} time to exhaustion", label),
+                        &format!("{:.0} min", minutes),
+                        &format!("{} min", warning_mins),
+                        format!(
+                            "At the current rate your {} limit runs out in about {:.0} minutes, before it resets",
+                            label, minutes
                         ),
-                    );
+                    ),
+                })
+            })
+            .collect()
+    }
+
+    fn legacy_usage_readings(status: &ProviderStatus, threshold: &NotificationThreshold) -> Vec<Reading> {
+        let mut readings = Vec::new();
+        let Some(usage) = &status.usage else {
+            return readings;
+        };
+
+        if let Some(token_limit) = threshold.token_limit {
+            readings.push(Reading {
+                metric: "tokens",
+                value: usage.total_tokens as f64,
+                limit: token_limit as f64,
+                direction: Direction::Above,
+                title: format!("{} Token Limit Reached", status.provider),
+                body: threshold.render_body(
+                    &status.provider,
+                    "tokens",
+                    &usage.total_tokens.to_string(),
+                    &token_limit.to_string(),
+                    format!(
+                        "You've used {} tokens (limit: {})",
+                        usage.total_tokens, token_limit
+                    ),
+                ),
+            });
+        }
+
+        if let (Some(cost_limit), Some(cost)) = (threshold.cost_limit, usage.cost) {
+            readings.push(Reading {
+                metric: "cost",
+                value: cost,
+                limit: cost_limit,
+                direction: Direction::Above,
+                title: format!("{} Cost Limit Reached", status.provider),
+                body: threshold.render_body(
+                    &status.provider,
+                    "cost",
+                    &format!("${:.2}", cost),
+                    &format!("${:.2}", cost_limit),
+                    format!("You've spent ${:.2} (limit: ${:.2})", cost, cost_limit),
+                ),
+            });
+        }
+
+        readings
+    }
+
+    fn rate_limit_readings(status: &ProviderStatus, threshold: &NotificationThreshold) -> Vec<Reading> {
+        let mut readings = Vec::new();
+        let Some(rate_limit) = &status.rate_limit else {
+            return readings;
+        };
+
+        if let (Some(limit), Some(remaining), Some(pct_threshold)) = (
+            rate_limit.limit,
+            rate_limit.remaining,
+            threshold.rate_limit_percentage,
+        ) {
+            if limit == 0 {
+                return readings;
+            }
+            let percentage = (remaining as f64 / limit as f64) * 100.0;
+            readings.push(Reading {
+                metric: "rate_limit",
+                value: percentage,
+                limit: pct_threshold as f64,
+                direction: Direction::Below,
+                title: format!("{} Rate Limit Warning", status.provider),
+                body: threshold.render_body(
+                    &status.provider,
+                    "requests remaining",
+                    &format!("{:.1}%", percentage),
+                    &format!("{}%", pct_threshold),
+                    format!(
+                        "Only {} of {} requests remaining ({:.1}%)",
+                        remaining, limit, percentage
+                    ),
+                ),
+            });
+        }
+
+        readings
+    }
+
+    fn send_notification(&mut self, app: &tauri::AppHandle, alert: AlertMessage, context: AlertContext) {
+        let quiet = self.quiet_hours.is_active(chrono::Utc::now())
+            && !(self.quiet_hours.allow_critical && alert.severity == AlertSeverity::Critical);
+
+        let desktop = if quiet {
+            if self.quiet_queue.len() >= MAX_QUIET_QUEUE {
+                self.quiet_queue.remove(0);
+                self.quiet_dropped += 1;
+            }
+            self.quiet_queue.push(alert.clone());
+            if let Err(e) = settings::save(app, settings::QUIET_QUEUE, &self.quiet_queue) {
+                eprintln!("Failed to persist quiet hours queue: {}", e);
+            }
+            DeliveryResult::new("desktop", DeliveryStatus::Queued, Some("Quiet hours".to_string()))
+        } else {
+            match show_app_notification(app, &alert.title, &alert.body) {
+                Ok(()) => DeliveryResult::new("desktop", DeliveryStatus::Delivered, None),
+                Err(err) => {
+                    eprintln!("Failed to send notification '{}': {}", alert.title, err);
+                    DeliveryResult::new("desktop", DeliveryStatus::Failed, Some(err))
                 }
             }
+        };
+        let record_id = self.record(app, &alert, context, vec![desktop]);
 
-            if let Some(cost_limit) = threshold.cost_limit {
-                if let Some(cost) = usage.cost {
-                    if cost >= cost_limit {
-                        self.send_notification(
-                            app,
-                            &format!("{} Cost Limit Reached", status.provider),
-                            &format!("You've spent ${:.2} (limit: ${:.2})", cost, cost_limit),
-                        );
+        let client = self.http.client();
+        // Quiet hours only silence this desktop; shared channels still get the alert.
+        for sink in self.sinks.iter().filter(|s| s.accepts(&alert)).cloned() {
+            let client = client.clone();
+            let alert = alert.clone();
+            let history = self.history.clone();
+            let app = app.clone();
+            let shutdown = self.shutdown.clone();
+            tauri::async_runtime::spawn(async move {
+                let result = match shutdown.run_until_cancelled(sink.deliver(&client, &alert)).await {
+                    Some(Ok(())) => DeliveryResult::new(&sink.name, DeliveryStatus::Delivered, None),
+                    Some(Err(err)) => {
+                        eprintln!("Failed to deliver '{}' to sink '{}': {}", alert.title, sink.name, err);
+                        DeliveryResult::new(&sink.name, DeliveryStatus::Failed, Some(err))
                     }
+                    None => DeliveryResult::new(&sink.name, DeliveryStatus::Failed, Some("Cancelled on quit".to_string())),
+                };
+
+                let mut history = history.lock().unwrap();
+                history.record_delivery(record_id, result);
+                if let Err(e) = history.persist(&app) {
+                    eprintln!("Failed to persist notification history: {}", e);
                 }
-            }
+            });
         }
     }
 
-    fn check_rate_limit(
+    /// Adds an entry to the notification history and returns its id.
+    fn record(
         &self,
-        status: &ProviderStatus,
-        threshold: &NotificationThreshold,
         app: &tauri::AppHandle,
-    ) {
-        if let Some(rate_limit) = &status.rate_limit {
-            if let (Some(limit), Some(remaining), Some(pct_threshold)) = (
-                rate_limit.limit,
-                rate_limit.remaining,
-                threshold.rate_limit_percentage,
-            ) {
-                let percentage = (remaining as f64 / limit as f64) * 100.0;
-                if percentage < pct_threshold as f64 {
-                    self.send_notification(
-                        app,
-                        &format!("{} Rate Limit Warning", status.provider),
-                        &format!(
-                            "Only {} of {} requests remaining ({:.1}%)",
-                            remaining, limit, percentage
-                        ),
-                    );
-                }
-            }
+        alert: &AlertMessage,
+        context: AlertContext,
+        deliveries: Vec<DeliveryResult>,
+    ) -> u64 {
+        let mut history = self.history.lock().unwrap();
+        let id = history.push(alert, context, deliveries);
+        if let Err(e) = history.persist(app) {
+            eprintln!("Failed to persist notification history: {}", e);
         }
+        id
     }
+}
 
-    fn send_notification(&self, app: &tauri::AppHandle, title: &str, body: &str) {
-        if let Err(err) = show_app_notification(app, title, body) {
-            eprintln!("Failed to send notification '{}': {}", title, err);
-        }
-    }
+pub(crate) fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
 }
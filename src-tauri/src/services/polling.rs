@@ -1,22 +1,490 @@
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::time::Duration;
-use tokio::sync::RwLock;
-use tokio::time::interval;
-use tauri::AppHandle;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{watch, Notify, RwLock};
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+use tauri::{AppHandle, Emitter};
 use tauri::image::Image;
 
 use crate::api::claude_oauth::ClaudeOAuthClient;
+use crate::api::http::{self, HttpClient};
 use crate::api::openai::OpenAIClient;
 use crate::storage::keychain::KeychainManager;
-use super::{ProviderStatus, ClaudeUsageSnapshot, CodexUsageSnapshot, NotificationService};
+use super::power::PowerState;
+use super::{ProviderStatus, BackoffState, ClaudeUsageSnapshot, CodexUsageSnapshot, NotificationService, UsageWindow};
 use crate::tray;
 
+/// A utilization drop this large between two polls is treated as a window reset
+/// even if the reported reset time has not passed yet.
+const SHARP_DROP_POINTS: f64 = 30.0;
+
+/// Poll at the minimum interval once a window is this full...
+const NEAR_LIMIT_PCT: f64 = 80.0;
+/// ...or this close to resetting.
+const NEAR_RESET: Duration = Duration::from_secs(10 * 60);
+/// Unchanged polls before the interval starts doubling.
+const IDLE_POLLS_PER_STEP: u32 = 3;
+const STALE_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+/// Spread regular polls by this fraction so installs don't poll in lockstep.
+const INTERVAL_JITTER: f64 = 0.1;
+const MAX_BACKOFF: Duration = Duration::from_secs(30 * 60);
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60 * 60);
+/// How often to check whether the network is back while offline.
+const OFFLINE_PROBE_INTERVAL: Duration = Duration::from_secs(15);
+const POWER_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+const SLEEP_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+/// Wall-clock time beyond the monotonic clock's that counts as a suspend.
+const SLEEP_JUMP_THRESHOLD: Duration = Duration::from_secs(30);
+/// Data is expected to be old right after a resume, so staleness isn't reported for this long.
+const RESUME_GRACE: Duration = Duration::from_secs(2 * 60);
+
+pub const STATUS_CHANGED_EVENT: &str = "provider-status-changed";
+/// Carries the approximate number of seconds spent asleep.
+pub const SYSTEM_RESUMED_EVENT: &str = "system-resumed";
+
+/// Increases with every status event, so the frontend can drop out-of-order ones.
+static STATUS_REVISION: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Clone, Serialize)]
+pub struct ProviderStatusEvent {
+    pub provider: String,
+    pub revision: u64,
+    pub status: ProviderStatus,
+}
+
+pub fn emit_status_changed(app: &AppHandle, status: &ProviderStatus) {
+    let event = ProviderStatusEvent {
+        provider: status.provider.clone(),
+        revision: STATUS_REVISION.fetch_add(1, Ordering::SeqCst) + 1,
+        status: status.clone(),
+    };
+    if let Err(e) = app.emit(STATUS_CHANGED_EVENT, event) {
+        eprintln!("Failed to emit status event: {}", e);
+    }
+}
+
+/// Polling cadence for one provider. With `adaptive` on, the interval drops to
+/// `min_interval_secs` near a limit or reset and grows towards `max_interval_secs`
+/// while usage is unchanged or the provider is disconnected.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProviderPollSettings {
+    pub interval_secs: u64,
+    pub min_interval_secs: u64,
+    pub max_interval_secs: u64,
+    pub adaptive: bool,
+}
+
+impl Default for ProviderPollSettings {
+    fn default() -> Self {
+        Self {
+            interval_secs: 60,
+            min_interval_secs: 30,
+            max_interval_secs: 600,
+            adaptive: true,
+        }
+    }
+}
+
+impl ProviderPollSettings {
+    fn validate(&self, provider: &str) -> Result<(), String> {
+        if self.min_interval_secs < 10 {
+            return Err(format!("{}: minimum interval must be at least 10 seconds", provider));
+        }
+        if !(self.min_interval_secs..=self.max_interval_secs).contains(&self.interval_secs) {
+            return Err(format!("{}: interval must be between the minimum and maximum", provider));
+        }
+        Ok(())
+    }
+}
+
+/// Slower polling on battery or a metered connection: provider intervals are
+/// raised to at least these bounds while the profile is in effect.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LowPowerProfile {
+    pub on_battery: bool,
+    pub on_metered: bool,
+    pub min_interval_secs: u64,
+    pub max_interval_secs: u64,
+}
+
+impl Default for LowPowerProfile {
+    fn default() -> Self {
+        Self {
+            on_battery: true,
+            on_metered: true,
+            min_interval_secs: 300,
+            max_interval_secs: 1800,
+        }
+    }
+}
+
+impl LowPowerProfile {
+    fn applies(&self, power: PowerState) -> bool {
+        (self.on_battery && power.on_battery) || (self.on_metered && power.metered)
+    }
+
+    fn apply(&self, settings: &ProviderPollSettings) -> ProviderPollSettings {
+        let min_interval_secs = settings.min_interval_secs.max(self.min_interval_secs);
+        let max_interval_secs = settings.max_interval_secs.max(self.max_interval_secs);
+        ProviderPollSettings {
+            interval_secs: settings.interval_secs.clamp(min_interval_secs, max_interval_secs),
+            min_interval_secs,
+            max_interval_secs,
+            adaptive: settings.adaptive,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PollSettings {
+    pub anthropic: ProviderPollSettings,
+    pub openai: ProviderPollSettings,
+    /// Mark a provider stale after this many poll intervals without fresh data.
+    pub stale_after_missed: u32,
+    pub notify_when_stale: bool,
+    pub low_power: LowPowerProfile,
+}
+
+impl Default for PollSettings {
+    fn default() -> Self {
+        Self {
+            anthropic: ProviderPollSettings::default(),
+            openai: ProviderPollSettings::default(),
+            stale_after_missed: 3,
+            notify_when_stale: false,
+            low_power: LowPowerProfile::default(),
+        }
+    }
+}
+
+impl PollSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.stale_after_missed == 0 {
+            return Err("Stale threshold must be at least one missed interval".to_string());
+        }
+        if self.low_power.min_interval_secs < 10 {
+            return Err("Low-power minimum interval must be at least 10 seconds".to_string());
+        }
+        if self.low_power.max_interval_secs < self.low_power.min_interval_secs {
+            return Err("Low-power maximum interval must not be below the minimum".to_string());
+        }
+        self.anthropic.validate("anthropic")?;
+        self.openai.validate("openai")
+    }
+}
+
+/// Lets callers ask for an immediate poll of one provider and wait for it.
+struct RefreshSignal {
+    requested: AtomicBool,
+    in_flight: AtomicBool,
+    /// Bumped after every completed poll.
+    polls: watch::Sender<u64>,
+    /// Regular interval until the next poll, for the staleness watchdog.
+    expected_interval_secs: AtomicU64,
+}
+
+impl Default for RefreshSignal {
+    fn default() -> Self {
+        Self {
+            requested: AtomicBool::new(false),
+            in_flight: AtomicBool::new(false),
+            polls: watch::channel(0).0,
+            expected_interval_secs: AtomicU64::new(0),
+        }
+    }
+}
+
+impl RefreshSignal {
+    fn start(&self) {
+        self.requested.store(false, Ordering::SeqCst);
+        self.in_flight.store(true, Ordering::SeqCst);
+    }
+
+    fn finish(&self, expected_interval: Duration) {
+        self.expected_interval_secs.store(expected_interval.as_secs(), Ordering::SeqCst);
+        self.in_flight.store(false, Ordering::SeqCst);
+        self.polls.send_modify(|n| *n += 1);
+    }
+
+    /// Resolves a pending refresh without polling.
+    fn cancel(&self) {
+        if self.requested.swap(false, Ordering::SeqCst) {
+            self.polls.send_modify(|n| *n += 1);
+        }
+    }
+}
+
+/// Shared with commands so poll settings can change while the loop is running.
+#[derive(Clone, Default)]
+pub struct PollingHandle {
+    pub settings: Arc<RwLock<PollSettings>>,
+    wake: Arc<Notify>,
+    anthropic: Arc<RefreshSignal>,
+    openai: Arc<RefreshSignal>,
+    offline: Arc<AtomicBool>,
+    /// Unix seconds of the last resume from sleep, 0 if none was seen.
+    resumed_at: Arc<AtomicU64>,
+    power: Arc<RwLock<PowerState>>,
+    /// Cancelled when the app shuts down; the polling loop stops with it.
+    shutdown: CancellationToken,
+}
+
+impl PollingHandle {
+    pub fn new(shutdown: CancellationToken) -> Self {
+        Self {
+            shutdown,
+            ..Self::default()
+        }
+    }
+
+    /// Makes the scheduler re-plan with the current settings and statuses.
+    pub fn wake(&self) {
+        self.wake.notify_one();
+    }
+
+    /// Polls `provider` now and resolves once that poll has finished. Calls made
+    /// while a poll is already running wait for it instead of starting another.
+    pub async fn refresh(&self, provider: &str) -> Result<(), String> {
+        let signal = match provider {
+            "anthropic" => &self.anthropic,
+            "openai" => &self.openai,
+            _ => return Err(format!("Provider '{}' is not polled", provider)),
+        };
+
+        let mut polls = signal.polls.subscribe();
+        polls.mark_unchanged();
+        if !signal.in_flight.load(Ordering::SeqCst) {
+            signal.requested.store(true, Ordering::SeqCst);
+            self.wake();
+        }
+        // The loop won't poll again once shutdown starts.
+        tokio::select! {
+            changed = polls.changed() => changed.map_err(|_| "Polling has stopped".to_string())?,
+            _ = self.shutdown.cancelled() => return Err("Polling has stopped".to_string()),
+        }
+        if self.is_offline() {
+            return Err("Offline, showing the last data received".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn is_offline(&self) -> bool {
+        self.offline.load(Ordering::SeqCst)
+    }
+
+    /// Like [`refresh_all`](Self::refresh_all), without waiting for the polls.
+    fn request_refresh_all(&self) {
+        for signal in [&self.anthropic, &self.openai] {
+            if !signal.in_flight.load(Ordering::SeqCst) {
+                signal.requested.store(true, Ordering::SeqCst);
+            }
+        }
+        self.wake();
+    }
+
+    pub async fn power_state(&self) -> PowerState {
+        *self.power.read().await
+    }
+
+    /// The configured settings, with the low-power profile applied if it is in effect.
+    async fn effective_settings(&self) -> PollSettings {
+        let mut settings = self.settings.read().await.clone();
+        if settings.low_power.applies(self.power_state().await) {
+            settings.anthropic = settings.low_power.apply(&settings.anthropic);
+            settings.openai = settings.low_power.apply(&settings.openai);
+        }
+        settings
+    }
+
+    fn resumed_recently(&self) -> bool {
+        let resumed_at = self.resumed_at.load(Ordering::SeqCst);
+        resumed_at > 0 && unix_now().saturating_sub(resumed_at) < RESUME_GRACE.as_secs()
+    }
+
+    pub async fn refresh_all(&self) -> Result<(), String> {
+        let (anthropic, openai) = tokio::join!(self.refresh("anthropic"), self.refresh("openai"));
+        anthropic.and(openai)
+    }
+}
+
+/// What one poll of a provider found, for scheduling the next.
+enum PollOutcome {
+    Success,
+    /// Nothing to poll, e.g. no credentials are configured.
+    Skipped,
+    Failed { retry_after: Option<Duration> },
+}
+
+/// When one provider is next due and what its interval is based on.
+struct ProviderSchedule {
+    last_polled: Option<Instant>,
+    next_due: Instant,
+    idle_polls: u32,
+    signature: Option<String>,
+    failures: u32,
+    /// The regular interval, ignoring jitter and backoff.
+    interval: Duration,
+}
+
+impl ProviderSchedule {
+    fn new(now: Instant) -> Self {
+        Self {
+            last_polled: None,
+            next_due: now,
+            idle_polls: 0,
+            signature: None,
+            failures: 0,
+            interval: Duration::ZERO,
+        }
+    }
+
+    fn polled(&mut self, settings: &ProviderPollSettings, status: &mut ProviderStatus, outcome: PollOutcome) {
+        let now = Instant::now();
+        self.last_polled = Some(now);
+
+        if let PollOutcome::Failed { retry_after } = outcome {
+            self.failures = self.failures.saturating_add(1);
+            let retry_after = retry_after.map(|d| d.min(MAX_RETRY_AFTER));
+            let delay = backoff_delay(settings, self.failures).max(retry_after.unwrap_or_default());
+            self.next_due = now + delay;
+            status.backoff = Some(BackoffState {
+                consecutive_failures: self.failures,
+                retry_at: unix_now() + delay.as_secs(),
+                server_requested: retry_after.is_some_and(|d| d >= delay),
+            });
+            return;
+        }
+
+        let signature = usage_signature(status);
+        if signature.is_some() && signature == self.signature {
+            self.idle_polls = self.idle_polls.saturating_add(1);
+        } else {
+            self.idle_polls = 0;
+        }
+        self.signature = signature;
+        self.failures = 0;
+        status.backoff = None;
+        if matches!(outcome, PollOutcome::Success) {
+            status.stale = false;
+        }
+        self.replan(settings, status);
+    }
+
+    fn replan(&mut self, settings: &ProviderPollSettings, status: &mut ProviderStatus) {
+        if self.failures > 0 {
+            // Something else (a login, a new API key) fixed the provider in the meantime.
+            if !status.connected || status.error.is_some() {
+                return;
+            }
+            self.failures = 0;
+            status.backoff = None;
+        }
+
+        if let Some(last_polled) = self.last_polled {
+            self.interval = adaptive_interval(settings, status, self.idle_polls);
+            self.next_due = last_polled + jitter(self.interval, INTERVAL_JITTER);
+        }
+    }
+}
+
+/// Exponential backoff from the provider's minimum interval, with "equal jitter":
+/// half the delay is fixed and half random, so retries from many installs spread out.
+fn backoff_delay(settings: &ProviderPollSettings, failures: u32) -> Duration {
+    let base = Duration::from_secs(settings.min_interval_secs.max(1));
+    let exp = base.saturating_mul(1 << (failures.saturating_sub(1)).min(16)).min(MAX_BACKOFF);
+    exp / 2 + exp.mul_f64(rand::random::<f64>() / 2.0)
+}
+
+/// Spawns `task` and drops it once `shutdown` is cancelled.
+fn spawn_until_cancelled(
+    shutdown: &CancellationToken,
+    task: impl std::future::Future<Output = ()> + Send + 'static,
+) -> tokio::task::JoinHandle<()> {
+    let shutdown = shutdown.clone();
+    tokio::spawn(async move {
+        shutdown.run_until_cancelled(task).await;
+    })
+}
+
+fn jitter(interval: Duration, fraction: f64) -> Duration {
+    interval.mul_f64(1.0 + fraction * (rand::random::<f64>() * 2.0 - 1.0))
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+fn is_stale(status: &ProviderStatus, expected_interval_secs: u64, missed: u32, now: u64) -> bool {
+    !status.offline
+        && status.connected
+        && status.last_updated > 0
+        && expected_interval_secs > 0
+        && now.saturating_sub(status.last_updated) > expected_interval_secs * missed.max(1) as u64
+}
+
+fn usage_signature(status: &ProviderStatus) -> Option<String> {
+    if !status.connected {
+        return None;
+    }
+    serde_json::to_string(&(&status.usage, &status.claude_usage, &status.codex_usage)).ok()
+}
+
+fn adaptive_interval(settings: &ProviderPollSettings, status: &ProviderStatus, idle_polls: u32) -> Duration {
+    let (min, max) = (settings.min_interval_secs, settings.max_interval_secs.max(settings.min_interval_secs));
+    let base = settings.interval_secs.clamp(min, max);
+    if !settings.adaptive {
+        return Duration::from_secs(base);
+    }
+
+    let secs = if !status.connected {
+        max
+    } else if near_limit_or_reset(status) {
+        min
+    } else {
+        let steps = (idle_polls / IDLE_POLLS_PER_STEP).min(16);
+        base.saturating_mul(1 << steps).min(max)
+    };
+    Duration::from_secs(secs)
+}
+
+fn near_limit_or_reset(status: &ProviderStatus) -> bool {
+    let Some(usage) = &status.claude_usage else {
+        return false;
+    };
+
+    let near_limit = usage.period_utilization >= NEAR_LIMIT_PCT
+        || usage.weekly_utilization.is_some_and(|u| u >= NEAR_LIMIT_PCT);
+
+    let now = chrono::Utc::now();
+    let near_reset = [&usage.period_resets_at, &usage.weekly_resets_at]
+        .into_iter()
+        .flatten()
+        .filter_map(|s| chrono::DateTime::parse_from_rfc3339(s).ok())
+        .any(|at| {
+            let until = at.with_timezone(&chrono::Utc) - now;
+            until >= chrono::Duration::zero() && until.to_std().is_ok_and(|d| d <= NEAR_RESET)
+        });
+
+    near_limit || near_reset
+}
+
 pub struct PollingService {
     anthropic_status: Arc<RwLock<ProviderStatus>>,
     openai_status: Arc<RwLock<ProviderStatus>>,
+    /// Not polled, but alert rules see its latest status.
+    gemini_status: Arc<RwLock<ProviderStatus>>,
     notification_service: Arc<RwLock<NotificationService>>,
     keychain: KeychainManager,
-    poll_interval: Duration,
+    handle: PollingHandle,
+    http: HttpClient,
     app_handle: AppHandle,
 }
 
@@ -25,58 +493,350 @@ impl PollingService {
         app_handle: AppHandle,
         anthropic_status: Arc<RwLock<ProviderStatus>>,
         openai_status: Arc<RwLock<ProviderStatus>>,
+        gemini_status: Arc<RwLock<ProviderStatus>>,
         notification_service: Arc<RwLock<NotificationService>>,
-        poll_interval_secs: u64,
+        handle: PollingHandle,
+        http: HttpClient,
     ) -> Self {
         Self {
             anthropic_status,
             openai_status,
+            gemini_status,
             notification_service,
             keychain: KeychainManager::new(),
-            poll_interval: Duration::from_secs(poll_interval_secs),
+            handle,
+            http,
             app_handle,
         }
     }
 
+    /// Runs until the handle's shutdown token is cancelled. A poll in progress at that point is
+    /// allowed to finish, so credentials and state are never left half-written.
     pub async fn start(self) {
         let anthropic_status = self.anthropic_status;
         let openai_status = self.openai_status;
+        let gemini_status = self.gemini_status;
         let notification_service = self.notification_service;
         let keychain = self.keychain;
-        let poll_interval = self.poll_interval;
+        let handle = self.handle;
+        let http = self.http;
+        let shutdown = handle.shutdown.clone();
         let app_handle = self.app_handle;
 
-        tokio::spawn(async move {
-            let mut ticker = interval(poll_interval);
-            
-            loop {
-                ticker.tick().await;
-                
-                Self::poll_anthropic(&anthropic_status, &openai_status, &notification_service, &app_handle).await;
-                Self::poll_openai(&openai_status, &keychain).await;
+        let watchers = [
+            spawn_until_cancelled(
+                &shutdown,
+                Self::watch_staleness(
+                    Arc::clone(&anthropic_status),
+                    Arc::clone(&openai_status),
+                    Arc::clone(&notification_service),
+                    handle.clone(),
+                    app_handle.clone(),
+                ),
+            ),
+            spawn_until_cancelled(&shutdown, Self::watch_power(handle.clone())),
+            spawn_until_cancelled(
+                &shutdown,
+                Self::watch_sleep(
+                    Arc::clone(&anthropic_status),
+                    Arc::clone(&notification_service),
+                    handle.clone(),
+                    app_handle.clone(),
+                ),
+            ),
+        ];
+
+        let now = Instant::now();
+        let mut anthropic = ProviderSchedule::new(now);
+        let mut openai = ProviderSchedule::new(now);
+
+        while !shutdown.is_cancelled() {
+            if handle.is_offline() {
+                if !Self::wait_for_network(&handle, &http, &shutdown).await {
+                    continue;
+                }
+                eprintln!("Network is reachable again, resuming polling");
+                Self::set_offline(false, &anthropic_status, &openai_status, &handle, &app_handle).await;
+                let now = Instant::now();
+                anthropic.next_due = now;
+                openai.next_due = now;
             }
-        });
+
+            tokio::select! {
+                _ = shutdown.cancelled() => break,
+                _ = tokio::time::sleep_until(anthropic.next_due.min(openai.next_due)) => {}
+                _ = handle.wake.notified() => {
+                    let settings = handle.effective_settings().await;
+                    anthropic.replan(&settings.anthropic, &mut *anthropic_status.write().await);
+                    openai.replan(&settings.openai, &mut *openai_status.write().await);
+
+                    let now = Instant::now();
+                    if handle.anthropic.requested.load(Ordering::SeqCst) {
+                        anthropic.next_due = now;
+                    }
+                    if handle.openai.requested.load(Ordering::SeqCst) {
+                        openai.next_due = now;
+                    }
+                    continue;
+                }
+            }
+
+            let now = Instant::now();
+            let settings = handle.effective_settings().await;
+
+            if anthropic.next_due <= now {
+                handle.anthropic.start();
+                let before = anthropic_status.read().await.clone();
+                let outcome =
+                    Self::poll_anthropic(&anthropic_status, &openai_status, &notification_service, &http, &app_handle).await;
+                if Self::went_offline(&outcome, &http).await {
+                    *anthropic_status.write().await = before;
+                    Self::set_offline(true, &anthropic_status, &openai_status, &handle, &app_handle).await;
+                } else {
+                    let mut status = anthropic_status.write().await;
+                    anthropic.polled(&settings.anthropic, &mut status, outcome);
+                    if !status.same_data(&before) {
+                        emit_status_changed(&app_handle, &status);
+                    }
+                }
+                handle.anthropic.finish(anthropic.interval);
+            }
+            if openai.next_due <= now && !handle.is_offline() {
+                handle.openai.start();
+                let before = openai_status.read().await.clone();
+                let outcome = Self::poll_openai(&openai_status, &keychain, &http).await;
+                if Self::went_offline(&outcome, &http).await {
+                    *openai_status.write().await = before;
+                    Self::set_offline(true, &anthropic_status, &openai_status, &handle, &app_handle).await;
+                } else {
+                    let mut status = openai_status.write().await;
+                    openai.polled(&settings.openai, &mut status, outcome);
+                    if !status.same_data(&before) {
+                        emit_status_changed(&app_handle, &status);
+                    }
+                }
+                handle.openai.finish(openai.interval);
+            }
+
+            let statuses = [
+                anthropic_status.read().await.clone(),
+                openai_status.read().await.clone(),
+                gemini_status.read().await.clone(),
+            ];
+            let mut notifier = notification_service.write().await;
+            notifier.evaluate_rules(&statuses, &app_handle);
+            notifier.flush_quiet_queue(&app_handle);
+        }
+
+        for watcher in watchers {
+            let _ = watcher.await;
+        }
+        eprintln!("Polling stopped");
+    }
+
+    /// A failed poll only counts against the provider if the network itself is up.
+    async fn went_offline(outcome: &PollOutcome, http: &HttpClient) -> bool {
+        matches!(outcome, PollOutcome::Failed { .. }) && !http::is_online(&http.client()).await
+    }
+
+    /// Waits for the next probe, or an earlier refresh request, and returns whether
+    /// the network is reachable again.
+    async fn wait_for_network(handle: &PollingHandle, http: &HttpClient, shutdown: &CancellationToken) -> bool {
+        tokio::select! {
+            _ = shutdown.cancelled() => return false,
+            _ = tokio::time::sleep(OFFLINE_PROBE_INTERVAL) => {}
+            _ = handle.wake.notified() => {}
+        }
+
+        let online = http::is_online(&http.client()).await;
+        if !online {
+            handle.anthropic.cancel();
+            handle.openai.cancel();
+        }
+        online
+    }
+
+    /// Flags every provider as offline or back online. Their data is left as it
+    /// was, so the last snapshot stays visible while polling is suspended.
+    async fn set_offline(
+        offline: bool,
+        anthropic_status: &Arc<RwLock<ProviderStatus>>,
+        openai_status: &Arc<RwLock<ProviderStatus>>,
+        handle: &PollingHandle,
+        app_handle: &AppHandle,
+    ) {
+        if handle.offline.swap(offline, Ordering::SeqCst) != offline && offline {
+            eprintln!("Network is unreachable, suspending polling");
+        }
+
+        let codex_connected = openai_status.read().await.connected;
+        for status_lock in [anthropic_status, openai_status] {
+            let mut status = status_lock.write().await;
+            if status.offline == offline {
+                continue;
+            }
+            status.offline = offline;
+            emit_status_changed(app_handle, &status);
+
+            if status.provider == "anthropic" {
+                match (&status.claude_usage, status.connected) {
+                    (Some(usage), true) => Self::update_tray_connected(
+                        usage.weekly_utilization.unwrap_or(0.0),
+                        usage.period_utilization,
+                        codex_connected,
+                        offline || status.stale,
+                        app_handle,
+                    ),
+                    _ => Self::update_tray_disconnected(codex_connected, app_handle),
+                }
+            }
+        }
+    }
+
+    /// Runs apart from the poll loop so that a stalled poll is still noticed.
+    async fn watch_staleness(
+        anthropic_status: Arc<RwLock<ProviderStatus>>,
+        openai_status: Arc<RwLock<ProviderStatus>>,
+        notification_service: Arc<RwLock<NotificationService>>,
+        handle: PollingHandle,
+        app_handle: AppHandle,
+    ) {
+        let mut ticker = tokio::time::interval(STALE_CHECK_INTERVAL);
+        loop {
+            ticker.tick().await;
+            if handle.resumed_recently() {
+                continue;
+            }
+            let settings = handle.settings.read().await.clone();
+            let codex_connected = openai_status.read().await.connected;
+
+            for (status_lock, signal) in [(&anthropic_status, &handle.anthropic), (&openai_status, &handle.openai)] {
+                let now = unix_now();
+                let mut status = status_lock.write().await;
+                let expected = signal.expected_interval_secs.load(Ordering::SeqCst);
+                let stale = is_stale(&status, expected, settings.stale_after_missed, now);
+                if stale == status.stale {
+                    continue;
+                }
+
+                status.stale = stale;
+                emit_status_changed(&app_handle, &status);
+                if let (true, Some(usage)) = (status.provider == "anthropic", &status.claude_usage) {
+                    Self::update_tray_connected(
+                        usage.weekly_utilization.unwrap_or(0.0),
+                        usage.period_utilization,
+                        codex_connected,
+                        stale,
+                        &app_handle,
+                    );
+                }
+
+                let (provider, minutes) = (status.provider.clone(), now.saturating_sub(status.last_updated) / 60);
+                drop(status);
+                if stale && settings.notify_when_stale {
+                    notification_service.write().await.notify_stale(&provider, minutes, &app_handle);
+                }
+            }
+        }
+    }
+
+    /// Re-plans polls whenever the machine switches between mains and battery, or
+    /// the connection between metered and unmetered.
+    async fn watch_power(handle: PollingHandle) {
+        let mut ticker = tokio::time::interval(POWER_CHECK_INTERVAL);
+        loop {
+            ticker.tick().await;
+            let power = PowerState::read().await;
+            let mut current = handle.power.write().await;
+            if *current == power {
+                continue;
+            }
+
+            *current = power;
+            drop(current);
+            eprintln!(
+                "Power state changed (on battery: {}, metered: {}), re-planning polls",
+                power.on_battery, power.metered
+            );
+            handle.wake();
+        }
+    }
+
+    /// On Linux and macOS the monotonic clock stands still while the machine is
+    /// suspended and the wall clock doesn't, so a gap between the two means we
+    /// have just woken up. Timers in the poll loop would otherwise fire only once
+    /// their remaining awake time has passed.
+    ///
+    /// Windows' monotonic clock keeps counting through sleep, so nothing is
+    /// detected there. Poll timers already fire right after a resume on Windows,
+    /// but the stale-alert grace period and cooldown adjustment don't apply.
+    async fn watch_sleep(
+        anthropic_status: Arc<RwLock<ProviderStatus>>,
+        notification_service: Arc<RwLock<NotificationService>>,
+        handle: PollingHandle,
+        app_handle: AppHandle,
+    ) {
+        let mut last = (std::time::Instant::now(), std::time::SystemTime::now());
+        loop {
+            tokio::time::sleep(SLEEP_CHECK_INTERVAL).await;
+            let now = (std::time::Instant::now(), std::time::SystemTime::now());
+            let wall = now.1.duration_since(last.1).unwrap_or_default();
+            let monotonic = now.0.duration_since(last.0);
+            last = now;
+
+            let Some(slept) = wall.checked_sub(monotonic).filter(|d| *d >= SLEEP_JUMP_THRESHOLD) else {
+                continue;
+            };
+
+            eprintln!("Resumed after about {} minutes asleep, refreshing", slept.as_secs() / 60);
+            handle.resumed_at.store(unix_now(), Ordering::SeqCst);
+            notification_service.write().await.discount_sleep(slept.as_secs(), &app_handle);
+            if let Err(e) = app_handle.emit(SYSTEM_RESUMED_EVENT, slept.as_secs()) {
+                eprintln!("Failed to emit resume event: {}", e);
+            }
+
+            // Reset countdowns in the tray menu were computed before the suspend.
+            let usage = anthropic_status.read().await.claude_usage.clone();
+            if let Some(usage) = usage {
+                if let Err(e) = crate::commands::update_tray_menu(
+                    app_handle.clone(),
+                    usage.weekly_utilization.unwrap_or(0.0),
+                    usage.period_utilization,
+                    usage.weekly_resets_at,
+                    usage.period_resets_at,
+                )
+                .await
+                {
+                    eprintln!("Failed to update tray menu: {}", e);
+                }
+            }
+
+            handle.request_refresh_all();
+        }
     }
 
     async fn poll_anthropic(
         anthropic_status: &Arc<RwLock<ProviderStatus>>,
         openai_status: &Arc<RwLock<ProviderStatus>>,
         notification_service: &Arc<RwLock<NotificationService>>,
+        http: &HttpClient,
         app_handle: &AppHandle,
-    ) {
+    ) -> PollOutcome {
         let codex_connected = openai_status.read().await.connected;
         
-        if !ClaudeOAuthClient::has_credentials() {
+        if !ClaudeOAuthClient::has_credentials().await {
             Self::handle_no_credentials(anthropic_status, codex_connected, app_handle).await;
-            return;
+            return PollOutcome::Skipped;
         }
 
-        match ClaudeOAuthClient::from_credentials_file_with_refresh().await {
+        match ClaudeOAuthClient::from_credentials_file_with_refresh(&http.client()).await {
             Ok(client) => {
-                Self::fetch_and_update_usage(client, anthropic_status, codex_connected, notification_service, app_handle).await;
+                Self::fetch_and_update_usage(client, anthropic_status, codex_connected, notification_service, app_handle).await
             }
             Err(e) => {
+                let retry_after = e.retry_after();
                 Self::handle_oauth_error(e, anthropic_status, codex_connected, app_handle).await;
+                PollOutcome::Failed { retry_after }
             }
         }
     }
@@ -87,39 +847,104 @@ impl PollingService {
         codex_connected: bool,
         notification_service: &Arc<RwLock<NotificationService>>,
         app_handle: &AppHandle,
-    ) {
+    ) -> PollOutcome {
         match client.get_usage().await {
             Ok(usage) => {
                 let period_util = usage.five_hour.as_ref().map(|u| u.utilization).unwrap_or(0.0);
                 let weekly_util = usage.seven_day.as_ref().map(|u| u.utilization);
+                let snapshot = ClaudeUsageSnapshot {
+                    period_utilization: period_util,
+                    period_resets_at: usage.five_hour.as_ref().map(|u| u.resets_at.clone()),
+                    weekly_utilization: weekly_util,
+                    weekly_resets_at: usage.seven_day.as_ref().map(|u| u.resets_at.clone()),
+                };
 
-                {
+                let previous = {
                     let mut status = anthropic_status.write().await;
                     status.connected = true;
                     status.error = None;
-                    status.claude_usage = Some(ClaudeUsageSnapshot {
-                        period_utilization: period_util,
-                        period_resets_at: usage.five_hour.as_ref().map(|u| u.resets_at.clone()),
-                        weekly_utilization: weekly_util,
-                        weekly_resets_at: usage.seven_day.as_ref().map(|u| u.resets_at.clone()),
-                    });
                     status.last_updated = std::time::SystemTime::now()
                         .duration_since(std::time::UNIX_EPOCH)
                         .unwrap()
                         .as_secs();
-                }
+                    status.claude_usage.replace(snapshot.clone())
+                };
+
+                Self::update_tray_connected(weekly_util.unwrap_or(0.0), period_util, codex_connected, false, app_handle);
 
-                Self::update_tray_connected(weekly_util.unwrap_or(0.0), period_util, codex_connected, app_handle);
+                let resets = previous
+                    .map(|previous| Self::detect_window_resets(&previous, &snapshot))
+                    .unwrap_or_default();
 
                 let status = anthropic_status.read().await;
                 let mut notifier = notification_service.write().await;
                 notifier.check_and_notify(&status, app_handle);
+                for (window, utilization) in resets {
+                    notifier.notify_window_reset(window, utilization, app_handle);
+                }
+                PollOutcome::Success
             }
             Err(e) => {
                 let mut status = anthropic_status.write().await;
                 status.error = Some(format!("Usage fetch error: {}", e));
+                PollOutcome::Failed { retry_after: e.retry_after() }
+            }
+        }
+    }
+
+    fn detect_window_resets(
+        previous: &ClaudeUsageSnapshot,
+        current: &ClaudeUsageSnapshot,
+    ) -> Vec<(UsageWindow, f64)> {
+        let now = chrono::Utc::now();
+        let mut resets = Vec::new();
+
+        if Self::window_reset(
+            previous.period_utilization,
+            previous.period_resets_at.as_deref(),
+            current.period_utilization,
+            current.period_resets_at.as_deref(),
+            now,
+        ) {
+            resets.push((UsageWindow::FiveHour, current.period_utilization));
+        }
+
+        if let (Some(previous_util), Some(current_util)) =
+            (previous.weekly_utilization, current.weekly_utilization)
+        {
+            if Self::window_reset(
+                previous_util,
+                previous.weekly_resets_at.as_deref(),
+                current_util,
+                current.weekly_resets_at.as_deref(),
+                now,
+            ) {
+                resets.push((UsageWindow::Weekly, current_util));
             }
         }
+
+        resets
+    }
+
+    /// A window has reset when its previous reset time has passed and a new one
+    /// was issued, or when utilization falls sharply between two polls.
+    fn window_reset(
+        previous_util: f64,
+        previous_resets_at: Option<&str>,
+        current_util: f64,
+        current_resets_at: Option<&str>,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> bool {
+        if current_util >= previous_util {
+            return false;
+        }
+
+        let reset_time_passed = previous_resets_at
+            .and_then(|s| chrono::DateTime::parse_from_rfc3339(s).ok())
+            .is_some_and(|at| at <= now)
+            && current_resets_at != previous_resets_at;
+
+        reset_time_passed || previous_util - current_util >= SHARP_DROP_POINTS
     }
 
     async fn handle_oauth_error(
@@ -157,10 +982,19 @@ impl PollingService {
         }
     }
 
-    fn update_tray_connected(weekly_util: f64, period_util: f64, codex_connected: bool, app_handle: &AppHandle) {
+    pub(crate) fn update_tray_connected(
+        weekly_util: f64,
+        period_util: f64,
+        codex_connected: bool,
+        stale: bool,
+        app_handle: &AppHandle,
+    ) {
         let weekly_remaining = 100.0 - weekly_util;
         let period_remaining = 100.0 - period_util;
-        let icon_data = tray::generate_tray_icon_with_codex(weekly_remaining, period_remaining, codex_connected);
+        let mut icon_data = tray::generate_tray_icon_with_codex(weekly_remaining, period_remaining, codex_connected);
+        if stale {
+            icon_data = tray::dim_icon(icon_data);
+        }
         let icon = Image::new_owned(icon_data, 22, 22);
         if let Some(tray) = app_handle.tray_by_id("main") {
             let _ = tray.set_icon(Some(icon));
@@ -182,50 +1016,54 @@ impl PollingService {
     async fn poll_openai(
         openai_status: &Arc<RwLock<ProviderStatus>>,
         keychain: &KeychainManager,
-    ) {
-        if let Ok(Some(api_key)) = keychain.get_api_key("openai") {
-            let client = OpenAIClient::new(api_key);
-            
-            match client.validate_key().await {
-                Ok(true) => {
-                    match client.get_organization_usage(30).await {
-                        Ok(usage) => {
-                            let mut status = openai_status.write().await;
-                            status.connected = true;
-                            status.error = None;
-                            status.codex_usage = Some(CodexUsageSnapshot {
-                                input_tokens: usage.input_tokens,
-                                output_tokens: usage.output_tokens,
-                                total_requests: usage.total_requests,
-                                total_cost_usd: usage.total_cost_usd,
-                                period_days: 30,
-                            });
-                            status.last_updated = std::time::SystemTime::now()
-                                .duration_since(std::time::UNIX_EPOCH)
-                                .unwrap()
-                                .as_secs();
-                        }
-                        Err(e) => {
-                            let mut status = openai_status.write().await;
-                            status.connected = true;
-                            status.error = Some(format!("Usage fetch error: {}", e));
-                            status.last_updated = std::time::SystemTime::now()
-                                .duration_since(std::time::UNIX_EPOCH)
-                                .unwrap()
-                                .as_secs();
-                        }
+        http: &HttpClient,
+    ) -> PollOutcome {
+        let Ok(Some(api_key)) = keychain.get_api_key("openai") else {
+            return PollOutcome::Skipped;
+        };
+        let client = OpenAIClient::with_client(api_key, http.client());
+        
+        match client.validate_key().await {
+            Ok(true) => {
+                match client.get_organization_usage(30).await {
+                    Ok(usage) => {
+                        let mut status = openai_status.write().await;
+                        status.connected = true;
+                        status.error = None;
+                        status.codex_usage = Some(CodexUsageSnapshot {
+                            input_tokens: usage.input_tokens,
+                            output_tokens: usage.output_tokens,
+                            total_requests: usage.total_requests,
+                            total_cost_usd: usage.total_cost_usd,
+                            period_days: 30,
+                        });
+                        status.last_updated = std::time::SystemTime::now()
+                            .duration_since(std::time::UNIX_EPOCH)
+                            .unwrap()
+                            .as_secs();
+                        PollOutcome::Success
+                    }
+                    Err(e) => {
+                        let mut status = openai_status.write().await;
+                        status.connected = true;
+                        status.error = Some(format!("Usage fetch error: {}", e));
+                        // `last_updated` keeps the time of the last good data, so
+                        // repeated failures show up as stale.
+                        PollOutcome::Failed { retry_after: e.retry_after() }
                     }
                 }
-                Ok(false) => {
-                    let mut status = openai_status.write().await;
-                    status.connected = false;
-                    status.error = Some("Invalid API key".to_string());
-                }
-                Err(e) => {
-                    let mut status = openai_status.write().await;
-                    status.connected = false;
-                    status.error = Some(format!("Error: {}", e));
-                }
+            }
+            Ok(false) => {
+                let mut status = openai_status.write().await;
+                status.connected = false;
+                status.error = Some("Invalid API key".to_string());
+                PollOutcome::Failed { retry_after: None }
+            }
+            Err(e) => {
+                let mut status = openai_status.write().await;
+                status.connected = false;
+                status.error = Some(format!("Error: {}", e));
+                PollOutcome::Failed { retry_after: e.retry_after() }
             }
         }
     }
@@ -0,0 +1,75 @@
+use serde::Serialize;
+
+const POWER_SUPPLY_DIR: &str = "/sys/class/power_supply";
+#[cfg(target_os = "linux")]
+const BUSCTL_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+
+#[derive(Clone, Copy, Default, PartialEq, Serialize)]
+pub struct PowerState {
+    pub on_battery: bool,
+    /// The active connection is metered, e.g. a phone hotspot.
+    pub metered: bool,
+}
+
+impl PowerState {
+    pub async fn read() -> Self {
+        Self {
+            on_battery: on_battery(),
+            metered: metered().await,
+        }
+    }
+}
+
+/// True when a system battery is discharging and no mains or USB supply is online.
+/// Always false where `/sys/class/power_supply` doesn't exist.
+fn on_battery() -> bool {
+    let Ok(entries) = std::fs::read_dir(POWER_SUPPLY_DIR) else {
+        return false;
+    };
+
+    let mut discharging = false;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let read = |name: &str| std::fs::read_to_string(path.join(name)).map(|s| s.trim().to_string()).ok();
+
+        match read("type").as_deref() {
+            Some("Mains") | Some("USB") if read("online").as_deref() == Some("1") => return false,
+            // Peripherals such as mice report `scope` "Device" and don't power the machine.
+            Some("Battery") if read("scope").as_deref() != Some("Device") => {
+                discharging |= read("status").as_deref() == Some("Discharging");
+            }
+            _ => {}
+        }
+    }
+    discharging
+}
+
+/// Asks NetworkManager over D-Bus whether the primary connection is metered,
+/// counting its guesses. False when NetworkManager or `busctl` isn't available.
+#[cfg(target_os = "linux")]
+async fn metered() -> bool {
+    let output = tokio::process::Command::new("busctl")
+        .args([
+            "get-property",
+            "org.freedesktop.NetworkManager",
+            "/org/freedesktop/NetworkManager",
+            "org.freedesktop.NetworkManager",
+            "Metered",
+        ])
+        .kill_on_drop(true)
+        .output();
+
+    match tokio::time::timeout(BUSCTL_TIMEOUT, output).await {
+        // Prints e.g. "u 4": 1 is yes, 3 guessed yes, 2 and 4 are no, 0 unknown.
+        Ok(Ok(output)) if output.status.success() => {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            matches!(stdout.split_whitespace().nth(1), Some("1") | Some("3"))
+        }
+        _ => false,
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+async fn metered() -> bool {
+    false
+}
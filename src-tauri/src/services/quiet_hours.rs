@@ -0,0 +1,98 @@
+use chrono::{DateTime, Datelike, NaiveTime, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TimeRange {
+    /// `HH:MM`, inclusive.
+    pub start: String,
+    /// `HH:MM`, exclusive. An end before the start spans midnight.
+    pub end: String,
+}
+
+/// A do-not-disturb schedule for desktop notifications.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QuietHours {
+    pub enabled: bool,
+    /// Days a range starts on, such as `"mon"` or `"saturday"`. Empty means every day.
+    pub days: Vec<String>,
+    pub ranges: Vec<TimeRange>,
+    /// IANA name such as `"Europe/Berlin"`. The system timezone is used when unset.
+    pub timezone: Option<String>,
+    /// Let critical alerts through instead of queueing them.
+    pub allow_critical: bool,
+}
+
+impl Default for QuietHours {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            days: Vec::new(),
+            ranges: vec![TimeRange {
+                start: "22:00".to_string(),
+                end: "07:00".to_string(),
+            }],
+            timezone: None,
+            allow_critical: true,
+        }
+    }
+}
+
+impl QuietHours {
+    pub fn validate(&self) -> Result<(), String> {
+        self.parse_days()?;
+        for range in &self.ranges {
+            parse_time(&range.start)?;
+            parse_time(&range.end)?;
+        }
+        if let Some(tz) = &self.timezone {
+            tz.parse::<Tz>().map_err(|_| format!("Unknown timezone '{}'", tz))?;
+        }
+        Ok(())
+    }
+
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        if !self.enabled {
+            return false;
+        }
+
+        match self.timezone.as_deref().and_then(|tz| tz.parse::<Tz>().ok()) {
+            Some(tz) => self.is_active_in(&tz, now),
+            None => self.is_active_in(&chrono::Local, now),
+        }
+    }
+
+    fn is_active_in<Z: TimeZone>(&self, tz: &Z, now: DateTime<Utc>) -> bool {
+        let Ok(days) = self.parse_days() else {
+            return false;
+        };
+        let local = now.with_timezone(tz);
+        let time = local.time();
+        let today = local.weekday();
+        let day_allowed = |day: Weekday| days.is_empty() || days.contains(&day);
+
+        self.ranges.iter().any(|range| {
+            let (Ok(start), Ok(end)) = (parse_time(&range.start), parse_time(&range.end)) else {
+                return false;
+            };
+            if start <= end {
+                day_allowed(today) && time >= start && time < end
+            } else {
+                // Overnight: the part after midnight belongs to the day the range started.
+                (day_allowed(today) && time >= start) || (day_allowed(today.pred()) && time < end)
+            }
+        })
+    }
+
+    fn parse_days(&self) -> Result<Vec<Weekday>, String> {
+        self.days
+            .iter()
+            .map(|d| d.parse::<Weekday>().map_err(|_| format!("Unknown day '{}'", d)))
+            .collect()
+    }
+}
+
+fn parse_time(value: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(value, "%H:%M").map_err(|_| format!("Invalid time '{}', expected HH:MM", value))
+}
@@ -11,6 +11,45 @@ pub struct ProviderStatus {
     pub rate_limit: Option<RateLimitInfo>,
     pub error: Option<String>,
     pub last_updated: u64,
+    #[serde(default)]
+    pub backoff: Option<BackoffState>,
+    /// No fresh data for several poll intervals; the numbers shown may be out of date.
+    #[serde(default)]
+    pub stale: bool,
+    /// The network is unreachable; the data shown is the last snapshot from before.
+    #[serde(default)]
+    pub offline: bool,
+}
+
+impl ProviderStatus {
+    /// Compares everything except `last_updated`, which changes on every poll.
+    pub fn same_data(&self, other: &Self) -> bool {
+        let data = |s: &Self| {
+            serde_json::to_value((
+                s.connected,
+                &s.usage,
+                &s.claude_usage,
+                &s.codex_usage,
+                &s.rate_limit,
+                &s.error,
+                &s.backoff,
+                s.stale,
+                s.offline,
+            ))
+            .ok()
+        };
+        data(self) == data(other)
+    }
+}
+
+/// Set while polling a provider is backing off after failed requests.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BackoffState {
+    pub consecutive_failures: u32,
+    /// Unix seconds of the next attempt.
+    pub retry_at: u64,
+    /// Whether the delay was set by the server's `Retry-After`.
+    pub server_requested: bool,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
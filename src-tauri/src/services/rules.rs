@@ -0,0 +1,256 @@
+use super::notification::AlertSeverity;
+use super::ProviderStatus;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const PROVIDERS: [&str; 3] = ["anthropic", "openai", "google"];
+const ANY_PROVIDER: &str = "any";
+const MAX_DEPTH: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleMetric {
+    /// Claude 5-hour window, in percent.
+    PeriodUtilization,
+    /// Claude weekly window, in percent.
+    WeeklyUtilization,
+    /// Spend in USD over the provider's reporting period.
+    Cost,
+    TotalTokens,
+    Requests,
+    /// Remaining requests as a percentage of the rate limit.
+    RateLimitRemaining,
+}
+
+impl RuleMetric {
+    fn value(self, status: &ProviderStatus) -> Option<f64> {
+        match self {
+            Self::PeriodUtilization => status.claude_usage.as_ref().map(|u| u.period_utilization),
+            Self::WeeklyUtilization => status.claude_usage.as_ref().and_then(|u| u.weekly_utilization),
+            Self::Cost => status
+                .codex_usage
+                .as_ref()
+                .map(|u| u.total_cost_usd)
+                .or_else(|| status.usage.as_ref().and_then(|u| u.cost)),
+            Self::TotalTokens => status
+                .codex_usage
+                .as_ref()
+                .map(|u| (u.input_tokens + u.output_tokens) as f64)
+                .or_else(|| status.usage.as_ref().map(|u| u.total_tokens as f64)),
+            Self::Requests => status.codex_usage.as_ref().map(|u| u.total_requests as f64),
+            Self::RateLimitRemaining => status.rate_limit.as_ref().and_then(|r| match (r.limit, r.remaining) {
+                (Some(limit), Some(remaining)) if limit > 0 => Some(remaining as f64 / limit as f64 * 100.0),
+                _ => None,
+            }),
+        }
+    }
+
+    fn is_percentage(self) -> bool {
+        matches!(self, Self::PeriodUtilization | Self::WeeklyUtilization | Self::RateLimitRemaining)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Comparison {
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+}
+
+impl Comparison {
+    fn holds(self, lhs: f64, rhs: f64) -> bool {
+        match self {
+            Self::Gt => lhs > rhs,
+            Self::Gte => lhs >= rhs,
+            Self::Lt => lhs < rhs,
+            Self::Lte => lhs <= rhs,
+        }
+    }
+
+    /// Moves `limit` by `margin` in the direction that makes the comparison easier to hold.
+    fn loosen(self, limit: f64, margin: f64) -> f64 {
+        match self {
+            Self::Gt | Self::Gte => limit - margin,
+            Self::Lt | Self::Lte => limit + margin,
+        }
+    }
+
+    fn symbol(self) -> &'static str {
+        match self {
+            Self::Gt => ">",
+            Self::Gte => ">=",
+            Self::Lt => "<",
+            Self::Lte => "<=",
+        }
+    }
+}
+
+/// A condition tree, e.g. `all[metric(anthropic, weekly_utilization > 80), metric(openai, cost > 200)]`.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RuleCondition {
+    All { conditions: Vec<RuleCondition> },
+    Any { conditions: Vec<RuleCondition> },
+    Not { condition: Box<RuleCondition> },
+    /// `provider` may be `"any"`, which holds if it holds for at least one provider.
+    Metric {
+        provider: String,
+        metric: RuleMetric,
+        op: Comparison,
+        value: f64,
+    },
+    /// Holds once a configured provider has been unreachable for at least `minutes`.
+    Disconnected { provider: String, minutes: u64 },
+}
+
+/// Inputs for one evaluation: the latest status of every provider and how long
+/// each failing provider has been down.
+pub struct RuleContext<'a> {
+    pub statuses: &'a [ProviderStatus],
+    pub down_since: &'a HashMap<String, u64>,
+    pub now: u64,
+}
+
+impl RuleContext<'_> {
+    fn matching<'s>(&'s self, provider: &'s str) -> impl Iterator<Item = &'s ProviderStatus> + 's {
+        self.statuses
+            .iter()
+            .filter(move |s| provider == ANY_PROVIDER || s.provider == provider)
+    }
+}
+
+impl RuleCondition {
+    pub fn evaluate(&self, ctx: &RuleContext) -> bool {
+        self.evaluate_with_margin(ctx, 0.0)
+    }
+
+    /// Evaluates with every metric limit loosened by `margin_pct` percent of itself.
+    /// A firing rule re-arms only once it is false even with the margin, so a value
+    /// wobbling around a limit doesn't notify again on every crossing.
+    pub fn evaluate_with_margin(&self, ctx: &RuleContext, margin_pct: f64) -> bool {
+        match self {
+            Self::All { conditions } => conditions.iter().all(|c| c.evaluate_with_margin(ctx, margin_pct)),
+            Self::Any { conditions } => conditions.iter().any(|c| c.evaluate_with_margin(ctx, margin_pct)),
+            // Loosening the inner condition would tighten this one.
+            Self::Not { condition } => !condition.evaluate_with_margin(ctx, -margin_pct),
+            Self::Metric { provider, metric, op, value } => {
+                let limit = op.loosen(*value, value.abs() * margin_pct / 100.0);
+                ctx.matching(provider)
+                    .filter(|s| s.connected)
+                    .filter_map(|s| metric.value(s))
+                    .any(|v| op.holds(v, limit))
+            }
+            Self::Disconnected { provider, minutes } => ctx.matching(provider).filter(|s| !s.offline).any(|s| {
+                ctx.down_since
+                    .get(&s.provider)
+                    .is_some_and(|&since| ctx.now.saturating_sub(since) >= minutes * 60)
+            }),
+        }
+    }
+
+    fn validate(&self, depth: usize) -> Result<(), String> {
+        if depth > MAX_DEPTH {
+            return Err(format!("Conditions may be nested at most {} levels deep", MAX_DEPTH));
+        }
+
+        match self {
+            Self::All { conditions } | Self::Any { conditions } => {
+                if conditions.is_empty() {
+                    return Err("'all' and 'any' need at least one condition".to_string());
+                }
+                conditions.iter().try_for_each(|c| c.validate(depth + 1))
+            }
+            Self::Not { condition } => condition.validate(depth + 1),
+            Self::Metric { provider, metric, value, .. } => {
+                validate_provider(provider)?;
+                if !value.is_finite() {
+                    return Err(format!("Invalid value for {:?}", metric));
+                }
+                if metric.is_percentage() && !(0.0..=100.0).contains(value) {
+                    return Err(format!("{:?} is a percentage, got {}", metric, value));
+                }
+                Ok(())
+            }
+            Self::Disconnected { provider, minutes } => {
+                validate_provider(provider)?;
+                if *minutes == 0 {
+                    return Err("Disconnected duration must be at least one minute".to_string());
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Human-readable form used as the default alert body.
+    pub fn describe(&self) -> String {
+        let join = |conditions: &[RuleCondition], sep: &str| {
+            let parts: Vec<String> = conditions.iter().map(|c| c.describe()).collect();
+            format!("({})", parts.join(sep))
+        };
+
+        match self {
+            Self::All { conditions } => join(conditions, " AND "),
+            Self::Any { conditions } => join(conditions, " OR "),
+            Self::Not { condition } => format!("NOT {}", condition.describe()),
+            Self::Metric { provider, metric, op, value } => {
+                let metric = serde_json::to_value(metric)
+                    .ok()
+                    .and_then(|v| v.as_str().map(str::to_string))
+                    .unwrap_or_default();
+                format!("{} {} {} {}", provider, metric, op.symbol(), value)
+            }
+            Self::Disconnected { provider, minutes } => {
+                format!("{} disconnected for {}+ min", provider, minutes)
+            }
+        }
+    }
+}
+
+fn validate_provider(provider: &str) -> Result<(), String> {
+    if provider == ANY_PROVIDER || PROVIDERS.contains(&provider) {
+        Ok(())
+    } else {
+        Err(format!("Unknown provider '{}'", provider))
+    }
+}
+
+/// An alert that fires when its condition becomes true and re-arms once it is false again.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AlertRule {
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    pub condition: RuleCondition,
+    #[serde(default)]
+    pub severity: AlertSeverity,
+    /// Alert body. Defaults to a description of the condition.
+    #[serde(default)]
+    pub message: Option<String>,
+    /// Repeat while the condition still holds after this many minutes. Never repeats when unset.
+    #[serde(default)]
+    pub repeat_cooldown_mins: Option<u64>,
+    /// How far, as a percentage of each metric limit, values have to move back
+    /// before the rule re-arms. Defaults to
+    /// [`DEFAULT_REARM_MARGIN_PCT`](super::notification::DEFAULT_REARM_MARGIN_PCT).
+    #[serde(default)]
+    pub rearm_margin_pct: Option<f64>,
+    pub enabled: bool,
+}
+
+impl AlertRule {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("Rule name is required".to_string());
+        }
+        if self.rearm_margin_pct.is_some_and(|m| !(0.0..=100.0).contains(&m)) {
+            return Err("Re-arm margin must be between 0 and 100 percent".to_string());
+        }
+        self.condition.validate(1)
+    }
+
+    pub fn body(&self) -> String {
+        self.message.clone().unwrap_or_else(|| self.condition.describe())
+    }
+}
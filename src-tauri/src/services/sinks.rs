@@ -0,0 +1,345 @@
+use super::notification::AlertSeverity;
+use crate::storage::keychain::KeychainManager;
+use lettre::message::{Mailbox, MultiPart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+const MAX_ATTEMPTS: u32 = 3;
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(30);
+const SMTP_TIMEOUT: Duration = Duration::from_secs(30);
+
+const DEFAULT_EMAIL_SUBJECT: &str = "[Agent Mana] {title}";
+const DEFAULT_EMAIL_TEXT: &str =
+    "{body}\n\nProvider: {provider}\nSeverity: {severity}\nTime: {timestamp}\n";
+const DEFAULT_EMAIL_HTML: &str = "<h2>{title}</h2><p>{body}</p>\
+<p><small>Provider: {provider} &middot; Severity: {severity} &middot; {timestamp}</small></p>";
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SmtpSecurity {
+    /// Plain connection upgraded with STARTTLS, usually port 587.
+    StartTls,
+    /// TLS from the first byte, usually port 465.
+    Tls,
+    /// Unencrypted, for local SMTP stand-ins such as Mailpit.
+    None,
+}
+
+impl SmtpSecurity {
+    fn default_port(self) -> u16 {
+        match self {
+            Self::StartTls => 587,
+            Self::Tls => 465,
+            Self::None => 25,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SinkTarget {
+    /// Slack incoming webhook.
+    Slack { webhook_url: String },
+    /// Discord channel webhook.
+    Discord { webhook_url: String },
+    /// Any endpoint accepting a JSON POST.
+    Json {
+        url: String,
+        #[serde(default)]
+        headers: HashMap<String, String>,
+    },
+    /// SMTP delivery. The password, if any, lives in the keychain under the sink id.
+    Email {
+        host: String,
+        #[serde(default)]
+        port: Option<u16>,
+        security: SmtpSecurity,
+        #[serde(default)]
+        username: Option<String>,
+        from: String,
+        to: Vec<String>,
+        /// Subject, plaintext and HTML bodies, with the same placeholders as `payload_template`.
+        #[serde(default)]
+        subject_template: Option<String>,
+        #[serde(default)]
+        text_template: Option<String>,
+        #[serde(default)]
+        html_template: Option<String>,
+    },
+}
+
+/// An outbound destination for alerts, in addition to the desktop notification.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct NotificationSink {
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    pub enabled: bool,
+    /// Alerts below this severity are not sent to the sink.
+    #[serde(default = "default_min_severity")]
+    pub min_severity: AlertSeverity,
+    /// JSON body with `{title}`, `{body}`, `{provider}`, `{severity}` and
+    /// `{timestamp}` placeholders. Each webhook kind has a sensible default.
+    #[serde(default)]
+    pub payload_template: Option<String>,
+    #[serde(flatten)]
+    pub target: SinkTarget,
+}
+
+fn default_min_severity() -> AlertSeverity {
+    AlertSeverity::Info
+}
+
+/// The alert as delivered to sinks; also the default generic JSON payload.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AlertMessage {
+    pub title: String,
+    pub body: String,
+    pub provider: String,
+    pub severity: AlertSeverity,
+    pub timestamp: String,
+}
+
+impl AlertMessage {
+    pub fn new(title: impl Into<String>, body: impl Into<String>, provider: impl Into<String>, severity: AlertSeverity) -> Self {
+        Self {
+            title: title.into(),
+            body: body.into(),
+            provider: provider.into(),
+            severity,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+}
+
+impl NotificationSink {
+    pub fn accepts(&self, alert: &AlertMessage) -> bool {
+        self.enabled && alert.severity >= self.min_severity
+    }
+
+    /// Checks the URL, headers and template so bad config fails at save time
+    /// rather than on the first alert.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("Sink name is required".to_string());
+        }
+
+        let sample = AlertMessage::new("Test", "Sample \"alert\" body", "anthropic", AlertSeverity::Warning);
+        if let SinkTarget::Email { host, .. } = &self.target {
+            if host.trim().is_empty() {
+                return Err("SMTP host is required".to_string());
+            }
+            return self.render_email(&sample).map(|_| ());
+        }
+
+        let url = self.url();
+        let parsed = reqwest::Url::parse(url).map_err(|e| format!("Invalid URL '{}': {}", url, e))?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return Err(format!("Unsupported URL scheme '{}'", parsed.scheme()));
+        }
+
+        self.headers()?;
+
+        self.render_payload(&sample)
+            .map(|_| ())
+            .map_err(|e| format!("Payload template does not produce valid JSON: {}", e))
+    }
+
+    fn url(&self) -> &str {
+        match &self.target {
+            SinkTarget::Slack { webhook_url } | SinkTarget::Discord { webhook_url } => webhook_url,
+            SinkTarget::Json { url, .. } => url,
+            SinkTarget::Email { host, .. } => host,
+        }
+    }
+
+    fn headers(&self) -> Result<HeaderMap, String> {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+
+        if let SinkTarget::Json { headers: custom, .. } = &self.target {
+            for (name, value) in custom {
+                let name = HeaderName::from_bytes(name.as_bytes())
+                    .map_err(|_| format!("Invalid header name '{}'", name))?;
+                let value = HeaderValue::from_str(value)
+                    .map_err(|_| format!("Invalid value for header '{}'", name))?;
+                headers.insert(name, value);
+            }
+        }
+
+        Ok(headers)
+    }
+
+    fn render_payload(&self, alert: &AlertMessage) -> Result<serde_json::Value, serde_json::Error> {
+        let template = match (&self.payload_template, &self.target) {
+            (Some(template), _) => template.as_str(),
+            (None, SinkTarget::Slack { .. }) => r#"{"text": "*{title}*\n{body}"}"#,
+            (None, SinkTarget::Discord { .. }) => r#"{"content": "**{title}**\n{body}"}"#,
+            (None, SinkTarget::Json { .. } | SinkTarget::Email { .. }) => return serde_json::to_value(alert),
+        };
+
+        serde_json::from_str(&render_template(template, alert, json_escape))
+    }
+
+    fn render_email(&self, alert: &AlertMessage) -> Result<Message, String> {
+        let SinkTarget::Email { from, to, subject_template, text_template, html_template, .. } = &self.target else {
+            return Err("Not an email sink".to_string());
+        };
+        if to.is_empty() {
+            return Err("At least one recipient is required".to_string());
+        }
+
+        let from: Mailbox = from.parse().map_err(|e| format!("Invalid sender '{}': {}", from, e))?;
+        let subject = render_template(subject_template.as_deref().unwrap_or(DEFAULT_EMAIL_SUBJECT), alert, |v| {
+            v.replace(['\r', '\n'], " ")
+        });
+        let text = render_template(text_template.as_deref().unwrap_or(DEFAULT_EMAIL_TEXT), alert, str::to_string);
+        let html = render_template(html_template.as_deref().unwrap_or(DEFAULT_EMAIL_HTML), alert, html_escape);
+
+        let mut builder = Message::builder().from(from).subject(subject);
+        for recipient in to {
+            let mailbox: Mailbox = recipient
+                .parse()
+                .map_err(|e| format!("Invalid recipient '{}': {}", recipient, e))?;
+            builder = builder.to(mailbox);
+        }
+
+        builder
+            .multipart(MultiPart::alternative_plain_html(text, html))
+            .map_err(|e| e.to_string())
+    }
+
+    fn smtp_transport(&self) -> Result<AsyncSmtpTransport<Tokio1Executor>, String> {
+        let SinkTarget::Email { host, port, security, username, .. } = &self.target else {
+            return Err("Not an email sink".to_string());
+        };
+
+        let builder = match security {
+            SmtpSecurity::StartTls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host),
+            SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(host),
+            SmtpSecurity::None => Ok(AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host)),
+        }
+        .map_err(|e| format!("Invalid SMTP host '{}': {}", host, e))?;
+
+        let mut builder = builder
+            .port(port.unwrap_or_else(|| security.default_port()))
+            .timeout(Some(SMTP_TIMEOUT));
+
+        if let Some(username) = username.as_ref().filter(|u| !u.is_empty()) {
+            let password = KeychainManager::new()
+                .get_smtp_password(&self.id)
+                .map_err(|e| format!("Failed to read SMTP password: {}", e))?
+                .unwrap_or_default();
+            builder = builder.credentials(Credentials::new(username.clone(), password));
+        }
+
+        Ok(builder.build())
+    }
+
+    /// Sends the alert by email, retrying transient SMTP failures with backoff.
+    async fn deliver_email(&self, alert: &AlertMessage) -> Result<(), String> {
+        let message = self.render_email(alert)?;
+        let transport = self.smtp_transport()?;
+
+        let mut delay = INITIAL_BACKOFF;
+        let mut attempt = 1;
+        loop {
+            let error = match transport.send(message.clone()).await {
+                Ok(_) => return Ok(()),
+                Err(e) if e.is_permanent() => return Err(e.to_string()),
+                Err(e) => e.to_string(),
+            };
+
+            if attempt >= MAX_ATTEMPTS {
+                return Err(error);
+            }
+
+            eprintln!(
+                "Delivery to sink '{}' failed (attempt {}), retrying in {:?}: {}",
+                self.name, attempt, delay, error
+            );
+            tokio::time::sleep(delay).await;
+            delay *= 2;
+            attempt += 1;
+        }
+    }
+
+    /// Posts the alert, retrying network errors, 429 and 5xx responses with
+    /// exponential backoff. `Retry-After` is honoured up to a short cap.
+    pub async fn deliver(&self, client: &reqwest::Client, alert: &AlertMessage) -> Result<(), String> {
+        if matches!(self.target, SinkTarget::Email { .. }) {
+            return self.deliver_email(alert).await;
+        }
+
+        let payload = self.render_payload(alert).map_err(|e| e.to_string())?;
+        let headers = self.headers()?;
+
+        let mut delay = INITIAL_BACKOFF;
+        let mut attempt = 1;
+        loop {
+            let result = client
+                .post(self.url())
+                .headers(headers.clone())
+                .json(&payload)
+                .send()
+                .await;
+
+            let (error, retry_after) = match result {
+                Ok(response) if response.status().is_success() => return Ok(()),
+                Ok(response) => {
+                    let status = response.status();
+                    let retry_after = crate::api::http::retry_after(response.headers());
+                    let text = response.text().await.unwrap_or_default();
+                    let error = format!("HTTP {}: {}", status.as_u16(), text);
+                    if !(status.as_u16() == 429 || status.is_server_error()) {
+                        return Err(error);
+                    }
+                    (error, retry_after)
+                }
+                Err(e) => (e.to_string(), None),
+            };
+
+            if attempt >= MAX_ATTEMPTS {
+                return Err(error);
+            }
+
+            let wait = retry_after.map(|d| d.min(MAX_RETRY_AFTER)).unwrap_or(delay);
+            eprintln!(
+                "Delivery to sink '{}' failed (attempt {}), retrying in {:?}: {}",
+                self.name, attempt, wait, error
+            );
+            tokio::time::sleep(wait).await;
+            delay *= 2;
+            attempt += 1;
+        }
+    }
+}
+
+fn render_template(template: &str, alert: &AlertMessage, escape: impl Fn(&str) -> String) -> String {
+    let severity = serde_json::to_value(alert.severity).unwrap_or_default();
+    template
+        .replace("{title}", &escape(&alert.title))
+        .replace("{body}", &escape(&alert.body))
+        .replace("{provider}", &escape(&alert.provider))
+        .replace("{severity}", &escape(severity.as_str().unwrap_or_default()))
+        .replace("{timestamp}", &escape(&alert.timestamp))
+}
+
+fn html_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\n', "<br>")
+}
+
+fn json_escape(value: &str) -> String {
+    let quoted = serde_json::Value::String(value.to_string()).to_string();
+    quoted[1..quoted.len() - 1].to_string()
+}
@@ -0,0 +1,235 @@
+use fs4::fs_std::FileExt;
+use serde_json::{Map, Value};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+const LOCK_FILE_NAME: &str = ".agent-mana-credentials.lock";
+/// Writers hold the lock for milliseconds; waiting longer means something is stuck.
+const LOCK_TIMEOUT: Duration = Duration::from_secs(2);
+const LOCK_RETRY: Duration = Duration::from_millis(20);
+
+/// Holds an exclusive advisory lock for as long as it is alive.
+///
+/// The lock lives in a sidecar file rather than on the credentials file itself,
+/// because the credentials file is replaced by rename and a lock on the old
+/// inode would not be seen by the next writer.
+///
+/// The Claude CLI doesn't take this lock, so it only serializes instances of this
+/// app. The atomic rename in [`update_json`] keeps the file from ever being torn,
+/// but a CLI write landing between our read and rename can still be overwritten.
+pub struct CredentialsLock {
+    _file: File,
+}
+
+impl CredentialsLock {
+    /// Blocks the calling thread for at most [`LOCK_TIMEOUT`].
+    pub fn acquire(creds_path: &Path) -> io::Result<Self> {
+        let dir = creds_path.parent().unwrap_or_else(|| Path::new("."));
+        fs::create_dir_all(dir)?;
+
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(dir.join(LOCK_FILE_NAME))?;
+
+        let deadline = Instant::now() + LOCK_TIMEOUT;
+        while !file.try_lock_exclusive()? {
+            if Instant::now() >= deadline {
+                return Err(io::Error::new(
+                    io::ErrorKind::WouldBlock,
+                    "timed out waiting for the credentials lock",
+                ));
+            }
+            std::thread::sleep(LOCK_RETRY);
+        }
+
+        Ok(Self { _file: file })
+    }
+}
+
+/// Reads the credentials file as raw JSON, keeping every key the Claude CLI stores.
+pub fn read_json(path: &Path) -> io::Result<Option<Value>> {
+    match fs::read_to_string(path) {
+        Ok(contents) => serde_json::from_str(&contents)
+            .map(Some)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Applies `update` to the current file contents under the advisory lock and
+/// atomically replaces the file with the result. Blocking; call it from
+/// `spawn_blocking` in async code.
+///
+/// Keys not touched by `update` are written back unchanged.
+pub fn update_json<F>(path: &Path, update: F) -> io::Result<()>
+where
+    F: FnOnce(&mut Map<String, Value>),
+{
+    let _lock = CredentialsLock::acquire(path)?;
+
+    let mut root = match read_json(path)? {
+        Some(Value::Object(map)) => map,
+        Some(_) => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "credentials file is not a JSON object",
+            ))
+        }
+        None => Map::new(),
+    };
+
+    update(&mut root);
+
+    let contents = serde_json::to_string_pretty(&Value::Object(root))
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    write_atomic(path, contents.as_bytes())
+}
+
+/// Merges `fields` into the `claudeAiOauth` object, creating it if needed.
+pub fn merge_oauth_fields(root: &mut Map<String, Value>, fields: Map<String, Value>) {
+    let entry = root
+        .entry("claudeAiOauth")
+        .or_insert_with(|| Value::Object(Map::new()));
+
+    if !entry.is_object() {
+        *entry = Value::Object(Map::new());
+    }
+
+    if let Value::Object(oauth) = entry {
+        oauth.extend(fields);
+    }
+}
+
+fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    fs::create_dir_all(dir)?;
+
+    let tmp_path = temp_path_for(path);
+    let result = (|| {
+        let mut file = open_private(&tmp_path)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, path)?;
+        sync_dir(dir);
+        Ok(())
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result
+}
+
+fn temp_path_for(path: &Path) -> PathBuf {
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "credentials".to_string());
+    path.with_file_name(format!(".{}.{}.tmp", file_name, std::process::id()))
+}
+
+#[cfg(unix)]
+fn open_private(path: &Path) -> io::Result<File> {
+    use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(true)
+        .write(true)
+        .mode(0o600)
+        .open(path)?;
+    // `mode` only applies on creation; enforce it for leftovers from an earlier crash.
+    file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    Ok(file)
+}
+
+#[cfg(not(unix))]
+fn open_private(path: &Path) -> io::Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .truncate(true)
+        .write(true)
+        .open(path)
+}
+
+#[cfg(unix)]
+fn sync_dir(dir: &Path) {
+    if let Ok(dir) = File::open(dir) {
+        let _ = dir.sync_all();
+    }
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn temp_credentials(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("agent-mana-test-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir.join(".credentials.json")
+    }
+
+    #[test]
+    fn refresh_write_keeps_unknown_keys() {
+        let path = temp_credentials("merge");
+        let original = json!({
+            "claudeAiOauth": {
+                "accessToken": "old-access",
+                "refreshToken": "old-refresh",
+                "expiresAt": 1,
+                "scopes": ["user:inference"],
+                "subscriptionType": "max",
+            },
+            "organizationUuid": "org-123",
+        });
+        fs::write(&path, original.to_string()).unwrap();
+
+        update_json(&path, |root| {
+            let mut fields = Map::new();
+            fields.insert("accessToken".into(), "new-access".into());
+            fields.insert("refreshToken".into(), "new-refresh".into());
+            fields.insert("expiresAt".into(), 2.into());
+            merge_oauth_fields(root, fields);
+        })
+        .unwrap();
+
+        let written = read_json(&path).unwrap().unwrap();
+        assert_eq!(
+            written,
+            json!({
+                "claudeAiOauth": {
+                    "accessToken": "new-access",
+                    "refreshToken": "new-refresh",
+                    "expiresAt": 2,
+                    "scopes": ["user:inference"],
+                    "subscriptionType": "max",
+                },
+                "organizationUuid": "org-123",
+            })
+        );
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn merge_replaces_a_non_object_oauth_entry() {
+        let mut root = Map::new();
+        root.insert("claudeAiOauth".into(), Value::Null);
+        root.insert("other".into(), json!(true));
+
+        let mut fields = Map::new();
+        fields.insert("accessToken".into(), "token".into());
+        merge_oauth_fields(&mut root, fields);
+
+        assert_eq!(Value::Object(root), json!({ "claudeAiOauth": { "accessToken": "token" }, "other": true }));
+    }
+}
@@ -26,4 +26,56 @@ impl KeychainManager {
         let entry = Entry::new("agent-mana", provider)?;
         entry.delete_credential()
     }
+
+    pub fn store_oauth_tokens(&self, provider: &str, tokens_json: &str) -> Result<(), keyring::Error> {
+        let entry = Entry::new("agent-mana", &Self::oauth_account(provider))?;
+        entry.set_password(tokens_json)
+    }
+
+    pub fn get_oauth_tokens(&self, provider: &str) -> Result<Option<String>, keyring::Error> {
+        let entry = Entry::new("agent-mana", &Self::oauth_account(provider))?;
+        match entry.get_password() {
+            Ok(tokens) => Ok(Some(tokens)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn delete_oauth_tokens(&self, provider: &str) -> Result<(), keyring::Error> {
+        let entry = Entry::new("agent-mana", &Self::oauth_account(provider))?;
+        match entry.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn store_smtp_password(&self, sink_id: &str, password: &str) -> Result<(), keyring::Error> {
+        let entry = Entry::new("agent-mana", &Self::smtp_account(sink_id))?;
+        entry.set_password(password)
+    }
+
+    pub fn get_smtp_password(&self, sink_id: &str) -> Result<Option<String>, keyring::Error> {
+        let entry = Entry::new("agent-mana", &Self::smtp_account(sink_id))?;
+        match entry.get_password() {
+            Ok(password) => Ok(Some(password)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn delete_smtp_password(&self, sink_id: &str) -> Result<(), keyring::Error> {
+        let entry = Entry::new("agent-mana", &Self::smtp_account(sink_id))?;
+        match entry.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn smtp_account(sink_id: &str) -> String {
+        format!("smtp-{}", sink_id)
+    }
+
+    fn oauth_account(provider: &str) -> String {
+        format!("{}-oauth", provider)
+    }
 }
@@ -1 +1,3 @@
 pub mod keychain;
+pub mod credentials_file;
+pub mod settings;
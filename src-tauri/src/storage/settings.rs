@@ -0,0 +1,46 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tauri::AppHandle;
+use tauri_plugin_store::StoreExt;
+
+/// Store file owned by the Rust side; the frontend keeps its own `settings.json`.
+const STORE_PATH: &str = "backend-settings.json";
+
+pub const TOKEN_REFRESH_POLICY: &str = "token_refresh_policy";
+pub const OAUTH_TOKEN_STORAGE: &str = "oauth_token_storage";
+pub const HTTP_SETTINGS: &str = "http_settings";
+pub const POLL_SETTINGS: &str = "poll_settings";
+pub const NOTIFICATION_THRESHOLDS: &str = "notification_thresholds";
+pub const ALERT_RULES: &str = "alert_rules";
+pub const ALERT_STATES: &str = "alert_states";
+pub const RESET_NOTIFICATIONS: &str = "reset_notifications";
+pub const NOTIFICATION_SINKS: &str = "notification_sinks";
+pub const QUIET_HOURS: &str = "quiet_hours";
+pub const QUIET_QUEUE: &str = "quiet_queue";
+pub const NOTIFICATION_HISTORY: &str = "notification_history";
+
+pub fn load<T: DeserializeOwned>(app: &AppHandle, key: &str) -> Option<T> {
+    let store = match app.store(STORE_PATH) {
+        Ok(store) => store,
+        Err(e) => {
+            eprintln!("Failed to open settings store: {}", e);
+            return None;
+        }
+    };
+
+    let value = store.get(key)?;
+    match serde_json::from_value(value) {
+        Ok(parsed) => Some(parsed),
+        Err(e) => {
+            eprintln!("Ignoring unreadable setting '{}': {}", key, e);
+            None
+        }
+    }
+}
+
+pub fn save<T: Serialize>(app: &AppHandle, key: &str, value: &T) -> Result<(), String> {
+    let store = app.store(STORE_PATH).map_err(|e| e.to_string())?;
+    let value = serde_json::to_value(value).map_err(|e| e.to_string())?;
+    store.set(key, value);
+    store.save().map_err(|e| e.to_string())
+}
@@ -216,6 +216,14 @@ pub fn generate_tray_icon_with_codex(
     img.into_raw()
 }
 
+/// Fades an icon to signal that what it shows may be out of date.
+pub fn dim_icon(mut rgba: Vec<u8>) -> Vec<u8> {
+    for pixel in rgba.chunks_exact_mut(4) {
+        pixel[3] /= 2;
+    }
+    rgba
+}
+
 pub fn generate_disconnected_icon() -> Vec<u8> {
     let mut img = RgbaImage::new(ICON_SIZE, ICON_SIZE);
 
@@ -1,6 +1,7 @@
-import { useState, useEffect, useCallback } from 'react';
+import { useState, useEffect, useCallback, useRef } from 'react';
 import { RefreshDouble, Settings } from 'iconoir-react';
 import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
 import { Store } from '@tauri-apps/plugin-store';
 import { ThemeProvider } from './contexts/ThemeContext';
 import CharacterCard, { CharacterStats } from './components/CharacterCard';
@@ -25,6 +26,12 @@ interface CodexUsageSnapshot {
   period_days: number;
 }
 
+interface BackoffState {
+  consecutive_failures: number;
+  retry_at: number;
+  server_requested: boolean;
+}
+
 interface ProviderStatus {
   provider: string;
   connected: boolean;
@@ -32,6 +39,15 @@ interface ProviderStatus {
   codex_usage: CodexUsageSnapshot | null;
   error: string | null;
   last_updated: number;
+  backoff: BackoffState | null;
+  stale: boolean;
+  offline: boolean;
+}
+
+interface ProviderStatusEvent {
+  provider: string;
+  revision: number;
+  status: ProviderStatus;
 }
 
 function AppContent() {
@@ -47,6 +63,9 @@ function AppContent() {
   const [notificationsEnabled, setNotificationsEnabled] = useState(true);
   const [triggeredCount, setTriggeredCount] = useState(0);
 
+  const statusesRef = useRef<Record<string, ProviderStatus>>({});
+  const lastRevisionRef = useRef(0);
+
   const loadAlerts = async () => {
     try {
       const store = await Store.load('settings.json');
@@ -225,80 +244,89 @@ function AppContent() {
       const anthropicStatus = await invoke<ProviderStatus>('get_provider_status', { provider: 'anthropic' });
       const openaiStatus = await invoke<ProviderStatus>('get_provider_status', { provider: 'openai' });
       const geminiStatus = await invoke<ProviderStatus>('get_provider_status', { provider: 'google' });
-      
-      setProviderConfigs(prev => prev.map(p => {
-        if (p.provider === 'anthropic') return { ...p, connected: anthropicStatus.connected, error: anthropicStatus.error };
-        if (p.provider === 'openai') return { ...p, connected: openaiStatus.connected, error: openaiStatus.error };
-        if (p.provider === 'google') return { ...p, connected: geminiStatus.connected, error: geminiStatus.error };
-        return p;
-      }));
-
-      setCharacterStats(prev => prev.map(s => {
-        if (s.provider === 'anthropic' && anthropicStatus.claude_usage) {
-          const usage = anthropicStatus.claude_usage;
+      statusesRef.current = { anthropic: anthropicStatus, openai: openaiStatus, google: geminiStatus };
+      await applyProviderStatus();
+    } catch (error) {
+      console.error('Failed to load provider status:', error);
+    }
+  };
+
+  const applyProviderStatus = async () => {
+    const { anthropic: anthropicStatus, openai: openaiStatus, google: geminiStatus } = statusesRef.current;
+    if (!anthropicStatus || !openaiStatus || !geminiStatus) return;
+
+    setProviderConfigs(prev => prev.map(p => {
+      if (p.provider === 'anthropic') return { ...p, connected: anthropicStatus.connected, error: anthropicStatus.error };
+      if (p.provider === 'openai') return { ...p, connected: openaiStatus.connected, error: openaiStatus.error };
+      if (p.provider === 'google') return { ...p, connected: geminiStatus.connected, error: geminiStatus.error };
+      return p;
+    }));
+
+    setCharacterStats(prev => prev.map(s => {
+      if (s.provider === 'anthropic' && anthropicStatus.claude_usage) {
+        const usage = anthropicStatus.claude_usage;
+        return {
+          ...s,
+          connected: anthropicStatus.connected,
+          error: anthropicStatus.error ?? undefined,
+          periodUtilization: usage.period_utilization,
+          periodResetTime: usage.period_resets_at ? new Date(usage.period_resets_at) : undefined,
+          weeklyUtilization: usage.weekly_utilization ?? 0,
+          weeklyResetTime: usage.weekly_resets_at ? new Date(usage.weekly_resets_at) : undefined,
+        };
+      }
+      if (s.provider === 'anthropic') {
+        return { ...s, connected: anthropicStatus.connected, error: anthropicStatus.error ?? undefined };
+      }
+      if (s.provider === 'openai') {
+        if (openaiStatus.codex_usage) {
+          const usage = openaiStatus.codex_usage;
+          const totalTokens = usage.input_tokens + usage.output_tokens;
           return {
             ...s,
-            connected: anthropicStatus.connected,
-            error: anthropicStatus.error ?? undefined,
-            periodUtilization: usage.period_utilization,
-            periodResetTime: usage.period_resets_at ? new Date(usage.period_resets_at) : undefined,
-            weeklyUtilization: usage.weekly_utilization ?? 0,
-            weeklyResetTime: usage.weekly_resets_at ? new Date(usage.weekly_resets_at) : undefined,
+            connected: openaiStatus.connected,
+            error: openaiStatus.error ?? undefined,
+            periodUtilization: 0,
+            weeklyUtilization: 0,
+            codexUsage: {
+              inputTokens: usage.input_tokens,
+              outputTokens: usage.output_tokens,
+              totalTokens,
+              totalCost: usage.total_cost_usd,
+              totalRequests: usage.total_requests,
+            },
           };
         }
-        if (s.provider === 'anthropic') {
-          return { ...s, connected: anthropicStatus.connected, error: anthropicStatus.error ?? undefined };
-        }
-        if (s.provider === 'openai') {
-          if (openaiStatus.codex_usage) {
-            const usage = openaiStatus.codex_usage;
-            const totalTokens = usage.input_tokens + usage.output_tokens;
-            return {
-              ...s,
-              connected: openaiStatus.connected,
-              error: openaiStatus.error ?? undefined,
-              periodUtilization: 0,
-              weeklyUtilization: 0,
-              codexUsage: {
-                inputTokens: usage.input_tokens,
-                outputTokens: usage.output_tokens,
-                totalTokens,
-                totalCost: usage.total_cost_usd,
-                totalRequests: usage.total_requests,
-              },
-            };
-          }
-          return { ...s, connected: openaiStatus.connected, error: openaiStatus.error ?? undefined };
-        }
-        if (s.provider === 'google') {
-          return { ...s, connected: geminiStatus.connected, error: geminiStatus.error ?? undefined };
-        }
-        return s;
-      }));
-
-      if (anthropicStatus.claude_usage) {
-        const usage = anthropicStatus.claude_usage;
-        const weeklyRemaining = 100 - (usage.weekly_utilization ?? 0);
-        const periodRemaining = 100 - usage.period_utilization;
-        try {
-          await invoke('update_tray_icon', { weeklyRemaining, periodRemaining });
-          await invoke('update_tray_menu', {
-            weeklyUtil: usage.weekly_utilization ?? 0,
-            periodUtil: usage.period_utilization,
-            weeklyReset: usage.weekly_resets_at,
-            periodReset: usage.period_resets_at,
-          });
-        } catch (e) {
-          console.warn('Failed to update tray:', e);
-        }
-
-        checkAlerts(usage.period_utilization, usage.weekly_utilization ?? 0);
+        return { ...s, connected: openaiStatus.connected, error: openaiStatus.error ?? undefined };
       }
-      
-      setLastRefreshTime(new Date());
-    } catch (error) {
-      console.error('Failed to load provider status:', error);
+      if (s.provider === 'google') {
+        return { ...s, connected: geminiStatus.connected, error: geminiStatus.error ?? undefined };
+      }
+      return s;
+    }));
+
+    if (anthropicStatus.claude_usage) {
+      const usage = anthropicStatus.claude_usage;
+      const weeklyRemaining = 100 - (usage.weekly_utilization ?? 0);
+      const periodRemaining = 100 - usage.period_utilization;
+      try {
+        await invoke('update_tray_icon', { weeklyRemaining, periodRemaining });
+        await invoke('update_tray_menu', {
+          weeklyUtil: usage.weekly_utilization ?? 0,
+          periodUtil: usage.period_utilization,
+          weeklyReset: usage.weekly_resets_at,
+          periodReset: usage.period_resets_at,
+        });
+      } catch (e) {
+        console.warn('Failed to update tray:', e);
+      }
+
+      checkAlerts(usage.period_utilization, usage.weekly_utilization ?? 0);
     }
+    
+    // Stale and offline events carry old data, so show when it was actually fetched.
+    const lastUpdated = Math.max(anthropicStatus.last_updated, openaiStatus.last_updated, geminiStatus.last_updated);
+    setLastRefreshTime(lastUpdated > 0 ? new Date(lastUpdated * 1000) : null);
   };
 
   const handleSaveKey = async (provider: string, apiKey: string): Promise<void> => {
@@ -329,6 +357,11 @@ function AppContent() {
 
   const handleRefresh = async () => {
     setRefreshing(true);
+    try {
+      await invoke('refresh_all');
+    } catch (error) {
+      console.error('Failed to refresh providers:', error);
+    }
     await loadProviderStatus();
     setRefreshing(false);
   };
@@ -341,8 +374,20 @@ function AppContent() {
   useEffect(() => {
     if (providerConfigs.length > 0) {
       loadProviderStatus();
-      const interval = setInterval(loadProviderStatus, 60000);
-      return () => clearInterval(interval);
+      const unlisten = listen<ProviderStatusEvent>('provider-status-changed', ({ payload }) => {
+        if (payload.revision <= lastRevisionRef.current) return;
+        lastRevisionRef.current = payload.revision;
+        statusesRef.current = { ...statusesRef.current, [payload.provider]: payload.status };
+        applyProviderStatus();
+      });
+      // Reset countdowns were computed before the machine went to sleep.
+      const unlistenResume = listen<number>('system-resumed', () => {
+        applyProviderStatus();
+      });
+      return () => {
+        unlisten.then(fn => fn());
+        unlistenResume.then(fn => fn());
+      };
     }
   }, [providerConfigs.length]);
 